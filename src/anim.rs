@@ -0,0 +1,227 @@
+//! Tick-driven animation helpers.
+//!
+//! Everything that blinks asks [`blink_on`] for its phase so that all
+//! blinking elements share one global speed, set with `#blinkspeed`. Page
+//! slides and the combat theme fade are shaped by one global [`Easing`],
+//! set with `#config easing`.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::TICKS_PER_SECOND;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlinkSpeed {
+    Slow,
+    Normal,
+    Fast,
+}
+
+impl BlinkSpeed {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "slow" => Some(Self::Slow),
+            "normal" => Some(Self::Normal),
+            "fast" => Some(Self::Fast),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Slow => "slow",
+            Self::Normal => "normal",
+            Self::Fast => "fast",
+        }
+    }
+
+    /// Length of one full on/off cycle in ticks.
+    pub fn period(self) -> u32 {
+        match self {
+            Self::Slow => 2 * TICKS_PER_SECOND,
+            Self::Normal => TICKS_PER_SECOND,
+            Self::Fast => TICKS_PER_SECOND / 2,
+        }
+    }
+
+    fn from_u8(v: u8) -> Self {
+        match v {
+            0 => Self::Slow,
+            2 => Self::Fast,
+            _ => Self::Normal,
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::Slow => 0,
+            Self::Normal => 1,
+            Self::Fast => 2,
+        }
+    }
+}
+
+static BLINK_SPEED: AtomicU8 = AtomicU8::new(1);
+
+pub fn blink_speed() -> BlinkSpeed {
+    BlinkSpeed::from_u8(BLINK_SPEED.load(Ordering::Relaxed))
+}
+
+pub fn set_blink_speed(speed: BlinkSpeed) {
+    BLINK_SPEED.store(speed.to_u8(), Ordering::Relaxed);
+}
+
+/// Whether a blinking element is in its visible half-cycle at `tick`.
+pub fn blink_phase(tick: u32, period: u32) -> bool {
+    let period = period.max(2);
+    tick % period < period / 2
+}
+
+/// [`blink_phase`] at the global blink speed.
+pub fn blink_on(tick: u32) -> bool {
+    blink_phase(tick, blink_speed().period())
+}
+
+/// How an animation's progress maps onto its motion.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Easing {
+    /// Constant speed.
+    Linear,
+    /// Slow at both ends, fastest in the middle.
+    EaseInOut,
+    /// Fast at first, settling gently.
+    #[default]
+    EaseOut,
+}
+
+impl Easing {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "linear" => Some(Self::Linear),
+            "inout" => Some(Self::EaseInOut),
+            "out" => Some(Self::EaseOut),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Linear => "linear",
+            Self::EaseInOut => "inout",
+            Self::EaseOut => "out",
+        }
+    }
+
+    fn from_u8(v: u8) -> Self {
+        match v {
+            0 => Self::Linear,
+            1 => Self::EaseInOut,
+            _ => Self::EaseOut,
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::Linear => 0,
+            Self::EaseInOut => 1,
+            Self::EaseOut => 2,
+        }
+    }
+}
+
+/// Progress `t` through an animation, shaped by `e` (cubic curves). `t` is
+/// clamped to `0.0..=1.0`, and the result runs from 0.0 to 1.0.
+pub fn ease(e: Easing, t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    match e {
+        Easing::Linear => t,
+        Easing::EaseInOut if t < 0.5 => 4.0 * t * t * t,
+        Easing::EaseInOut => {
+            let inv = 2.0 - 2.0 * t;
+            1.0 - inv * inv * inv / 2.0
+        }
+        Easing::EaseOut => {
+            let inv = 1.0 - t;
+            1.0 - inv * inv * inv
+        }
+    }
+}
+
+static EASING: AtomicU8 = AtomicU8::new(2);
+
+pub fn easing() -> Easing {
+    Easing::from_u8(EASING.load(Ordering::Relaxed))
+}
+
+pub fn set_easing(e: Easing) {
+    EASING.store(e.to_u8(), Ordering::Relaxed);
+}
+
+/// [`ease`] with the global easing.
+pub fn eased(t: f32) -> f32 {
+    ease(easing(), t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EASINGS: [Easing; 3] = [Easing::Linear, Easing::EaseInOut, Easing::EaseOut];
+
+    #[test]
+    fn every_easing_runs_from_zero_to_one() {
+        for e in EASINGS {
+            assert_eq!(ease(e, 0.0), 0.0, "{:?}", e);
+            assert_eq!(ease(e, 1.0), 1.0, "{:?}", e);
+            assert_eq!(ease(e, -1.0), 0.0, "{:?}", e);
+            assert_eq!(ease(e, 2.0), 1.0, "{:?}", e);
+        }
+    }
+
+    #[test]
+    fn every_easing_is_monotonic() {
+        for e in EASINGS {
+            let values: Vec<f32> = (0..=100).map(|i| ease(e, i as f32 / 100.0)).collect();
+            assert!(values.windows(2).all(|w| w[0] <= w[1]), "{:?}", e);
+        }
+    }
+
+    #[test]
+    fn curves_have_their_shape() {
+        assert_eq!(ease(Easing::Linear, 0.25), 0.25);
+        assert!(ease(Easing::EaseOut, 0.5) > 0.5);
+        assert!(ease(Easing::EaseInOut, 0.25) < 0.25);
+        assert!((ease(Easing::EaseInOut, 0.5) - 0.5).abs() < 1e-6);
+        assert!(ease(Easing::EaseInOut, 0.75) > 0.75);
+    }
+
+    #[test]
+    fn easings_round_trip_through_names() {
+        for e in EASINGS {
+            assert_eq!(Easing::parse(e.name()), Some(e));
+            assert_eq!(Easing::from_u8(e.to_u8()), e);
+        }
+        assert_eq!(Easing::default(), Easing::EaseOut);
+    }
+
+    #[test]
+    fn blink_is_on_for_first_half_of_period() {
+        let on: Vec<bool> = (0..8).map(|t| blink_phase(t, 4)).collect();
+        assert_eq!(on, [true, true, false, false, true, true, false, false]);
+    }
+
+    #[test]
+    fn degenerate_period_still_blinks() {
+        assert!(blink_phase(0, 0));
+        assert!(!blink_phase(1, 0));
+    }
+
+    #[test]
+    fn speeds_round_trip_through_names() {
+        for speed in [BlinkSpeed::Slow, BlinkSpeed::Normal, BlinkSpeed::Fast] {
+            assert_eq!(BlinkSpeed::parse(speed.name()), Some(speed));
+            assert_eq!(BlinkSpeed::from_u8(speed.to_u8()), speed);
+        }
+        assert!(BlinkSpeed::Slow.period() > BlinkSpeed::Normal.period());
+        assert!(BlinkSpeed::Normal.period() > BlinkSpeed::Fast.period());
+    }
+}
@@ -0,0 +1,75 @@
+//! Diagnostics bundle for bug reports (`#bugreport`).
+//!
+//! The most recent log messages are kept in memory so the report can include
+//! what the mod logged before a problem was noticed. The report itself is a
+//! plain text file of titled sections, written to the mod directory.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::config;
+use crate::ring::RingBuffer;
+
+/// Name of the report file inside the mod directory.
+pub const REPORT_FILE: &str = "bugreport.txt";
+
+/// Log messages kept for the report.
+pub const RECENT_LOG: usize = 64;
+
+static RECENT: Mutex<RingBuffer<String, RECENT_LOG>> = Mutex::new(RingBuffer::new());
+
+/// Keeps `text` as one of the recent log messages.
+pub fn remember(text: &str) {
+    RECENT.lock().unwrap_or_else(|e| e.into_inner()).push(text.to_string());
+}
+
+/// The recent log messages, oldest first.
+pub fn recent_log() -> Vec<String> {
+    RECENT.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect()
+}
+
+/// Renders `sections` as the report text: each title underlined, then its
+/// lines, with a blank line between sections. Empty sections say so.
+pub fn render(sections: &[(&str, Vec<String>)]) -> String {
+    let mut out = String::new();
+    for (title, lines) in sections {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("{}\n{}\n", title, "-".repeat(title.len())));
+        if lines.is_empty() {
+            out.push_str("(none)\n");
+        }
+        for line in lines {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Writes `text` to [`REPORT_FILE`] and returns its path.
+pub fn write(text: &str) -> std::io::Result<PathBuf> {
+    config::write_file(REPORT_FILE, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections_are_titled_and_separated() {
+        let text = render(&[("Version", vec!["Rust Demo Mod 1.0.0".to_string()]), ("Log", vec![])]);
+        assert_eq!(text, "Version\n-------\nRust Demo Mod 1.0.0\n\nLog\n---\n(none)\n");
+    }
+
+    #[test]
+    fn recent_log_drops_the_oldest() {
+        for i in 0..RECENT_LOG + 3 {
+            remember(&format!("line {}", i));
+        }
+        let log = recent_log();
+        assert_eq!(log.len(), RECENT_LOG);
+        assert_eq!(log[0], "line 3");
+    }
+}
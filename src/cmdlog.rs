@@ -0,0 +1,112 @@
+//! Audit log of handled commands (`#config cmdlog`).
+//!
+//! While enabled, every command the mod handles is appended to
+//! [`LOG_FILE`] in the mod directory as `<unix time> tick <tick>: <command>`.
+//! Lines are buffered and written in batches of [`FLUSH_LINES`], and
+//! whatever is left is written from `amod_exit`. If a write fails, logging
+//! turns itself off with a warning rather than retrying every command.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config;
+use crate::log;
+
+/// Name of the log file inside the mod directory.
+pub const LOG_FILE: &str = "commands.log";
+
+/// Buffered lines that trigger a write.
+pub const FLUSH_LINES: usize = 32;
+
+/// Append handled commands to the log (`#config cmdlog`).
+pub static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Lines waiting to be appended to [`LOG_FILE`].
+#[derive(Default)]
+pub struct CommandLog {
+    pending: Vec<String>,
+}
+
+impl CommandLog {
+    /// Buffers one entry. Returns true once enough lines are waiting that
+    /// they should be flushed.
+    pub fn push(&mut self, unix_secs: u64, tick: u32, cmd: &str) -> bool {
+        self.pending.push(format!("{} tick {}: {}\n", unix_secs, tick, cmd));
+        self.pending.len() >= FLUSH_LINES
+    }
+
+    /// Empties the buffer and returns its lines as one text, if there were
+    /// any.
+    pub fn take(&mut self) -> Option<String> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        Some(self.pending.drain(..).collect())
+    }
+}
+
+static LOG: Mutex<CommandLog> = Mutex::new(CommandLog { pending: Vec::new() });
+
+/// Appends the buffered lines through the settings store. They are dropped
+/// on failure too, so a broken file does not grow the buffer without bound.
+fn write(log_file: &mut CommandLog) {
+    let Some(text) = log_file.take() else {
+        return;
+    };
+    if let Err(err) = config::append_file(LOG_FILE, &text) {
+        ENABLED.store(false, Ordering::Relaxed);
+        log(&format!(
+            "Rust Demo Mod: cannot write {} ({}); command logging turned off",
+            config::mod_dir().join(LOG_FILE).display(),
+            err
+        ));
+    }
+}
+
+/// Records a handled command, if logging is on.
+pub fn record(tick: u32, cmd: &str) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let mut log_file = LOG.lock().unwrap_or_else(|e| e.into_inner());
+    if log_file.push(now, tick, cmd) {
+        write(&mut log_file);
+    }
+}
+
+/// Writes any buffered lines. Call from `amod_exit`.
+pub fn flush() {
+    write(&mut LOG.lock().unwrap_or_else(|e| e.into_inner()));
+}
+
+/// Turns logging off and forgets any buffered lines.
+pub fn reset() {
+    ENABLED.store(false, Ordering::Relaxed);
+    LOG.lock().unwrap_or_else(|e| e.into_inner()).pending.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffers_until_taken() {
+        let mut l = CommandLog::default();
+        assert_eq!(l.take(), None);
+        assert!(!l.push(1700000000, 24, "#overlay"));
+        l.push(1700000001, 48, "#stats");
+        assert_eq!(l.take().as_deref(), Some("1700000000 tick 24: #overlay\n1700000001 tick 48: #stats\n"));
+        assert_eq!(l.take(), None);
+    }
+
+    #[test]
+    fn asks_for_flush_when_batch_is_full() {
+        let mut l = CommandLog::default();
+        for i in 1..FLUSH_LINES {
+            assert!(!l.push(0, i as u32, "#clock"));
+        }
+        assert!(l.push(0, 0, "#clock"));
+    }
+}
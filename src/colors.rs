@@ -0,0 +1,272 @@
+//! Colors used by the mod.
+//!
+//! Engine colors are read from the client's globals on first use and cached;
+//! the client sets them up once at startup and never changes them. Colors the
+//! mod picks itself are plain constants.
+//!
+//! Drawing code takes its colors from the active [`Theme`] rather than from
+//! these directly.
+
+use std::sync::{Mutex, OnceLock};
+
+use crate::{healthcolor, manacolor, textcolor, whitecolor};
+
+/// Packs 5-bit red, green and blue channels into an RGB 5-5-5 color.
+pub const fn irgb(r: u16, g: u16, b: u16) -> u16 {
+    (r << 10) | (g << 5) | b
+}
+
+/// Converts hue (degrees, wraps), saturation and value (both `0.0..=1.0`)
+/// to an RGB 5-5-5 color.
+pub fn hsv_to_irgb(h: f32, s: f32, v: f32) -> u16 {
+    let h = h.rem_euclid(360.0) / 60.0;
+    let s = s.clamp(0.0, 1.0);
+    let v = v.clamp(0.0, 1.0);
+
+    let c = v * s;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = v - c;
+    let channel = |f: f32| ((f + m) * 31.0).round() as u16;
+    irgb(channel(r), channel(g), channel(b))
+}
+
+/// Blends from `a` (at `t = 0.0`) to `b` (at `t = 1.0`) channel by channel.
+pub fn lerp_irgb(a: u16, b: u16, t: f32) -> u16 {
+    let t = t.clamp(0.0, 1.0);
+    let channel = |shift: u16| {
+        let from = ((a >> shift) & 31) as f32;
+        let to = ((b >> shift) & 31) as f32;
+        (from + (to - from) * t).round() as u16
+    };
+    irgb(channel(10), channel(5), channel(0))
+}
+
+pub const GOLD: u16 = irgb(31, 31, 0);
+pub const COMBAT: u16 = irgb(31, 8, 8);
+pub const AFFORDABLE: u16 = irgb(8, 28, 8);
+pub const UNAFFORDABLE: u16 = irgb(31, 8, 8);
+pub const PANEL_BG: u16 = irgb(4, 4, 6);
+pub const PANEL_BORDER: u16 = irgb(12, 12, 16);
+pub const COMBAT_BORDER: u16 = irgb(22, 6, 6);
+pub const COMBAT_TITLE: u16 = irgb(31, 16, 14);
+pub const GRID: u16 = irgb(7, 7, 9);
+pub const GRID_LABEL: u16 = irgb(14, 14, 18);
+
+struct EngineColors {
+    white: u16,
+    text: u16,
+    health: u16,
+    mana: u16,
+}
+
+static ENGINE: OnceLock<EngineColors> = OnceLock::new();
+
+fn engine() -> &'static EngineColors {
+    ENGINE.get_or_init(|| unsafe {
+        EngineColors {
+            white: whitecolor,
+            text: textcolor,
+            health: healthcolor,
+            mana: manacolor,
+        }
+    })
+}
+
+pub fn white() -> u16 {
+    engine().white
+}
+
+pub fn text() -> u16 {
+    engine().text
+}
+
+pub fn health() -> u16 {
+    engine().health
+}
+
+pub fn mana() -> u16 {
+    engine().mana
+}
+
+/// The color of every element of the overlay panel.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Theme {
+    pub background: u16,
+    pub border: u16,
+    pub title: u16,
+    pub text: u16,
+    pub health: u16,
+    pub mana: u16,
+    pub gold: u16,
+    pub combat: u16,
+}
+
+impl Theme {
+    /// The built-in look: engine colors for text, mod colors for the rest.
+    pub fn standard() -> Self {
+        Self {
+            background: PANEL_BG,
+            border: PANEL_BORDER,
+            title: white(),
+            text: text(),
+            health: health(),
+            mana: mana(),
+            gold: GOLD,
+            combat: COMBAT,
+        }
+    }
+
+    /// This theme with a reddish border and title, used while in combat.
+    pub fn combat(&self) -> Self {
+        Self { border: COMBAT_BORDER, title: COMBAT_TITLE, ..*self }
+    }
+
+    /// Blends every element from this theme (`t = 0.0`) to `other`.
+    pub fn lerp(&self, other: &Theme, t: f32) -> Self {
+        Self {
+            background: lerp_irgb(self.background, other.background, t),
+            border: lerp_irgb(self.border, other.border, t),
+            title: lerp_irgb(self.title, other.title, t),
+            text: lerp_irgb(self.text, other.text, t),
+            health: lerp_irgb(self.health, other.health, t),
+            mana: lerp_irgb(self.mana, other.mana, t),
+            gold: lerp_irgb(self.gold, other.gold, t),
+            combat: lerp_irgb(self.combat, other.combat, t),
+        }
+    }
+
+    /// Element names and colors, in display order.
+    pub fn elements(&self) -> [(&'static str, u16); 8] {
+        [
+            ("background", self.background),
+            ("border", self.border),
+            ("title", self.title),
+            ("text", self.text),
+            ("health", self.health),
+            ("mana", self.mana),
+            ("gold", self.gold),
+            ("combat", self.combat),
+        ]
+    }
+
+    /// One `name: 0x318C` line per element, for `#theme dump`.
+    pub fn dump(&self) -> Vec<String> {
+        self.elements()
+            .iter()
+            .map(|(name, color)| format!("{}: 0x{:04X}", name, color))
+            .collect()
+    }
+}
+
+static ACTIVE: Mutex<Option<Theme>> = Mutex::new(None);
+
+/// Drops the active theme; the next [`theme`] call starts from the standard one.
+pub fn reset() {
+    *ACTIVE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// The theme drawing code should use right now.
+pub fn theme() -> Theme {
+    let mut active = ACTIVE.lock().unwrap_or_else(|e| e.into_inner());
+    *active.get_or_insert_with(Theme::standard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn irgb_packs_channels() {
+        assert_eq!(irgb(31, 0, 0), 0x7C00);
+        assert_eq!(irgb(0, 31, 0), 0x03E0);
+        assert_eq!(irgb(0, 0, 31), 0x001F);
+        assert_eq!(GOLD, 0x7FE0);
+    }
+
+    #[test]
+    fn dump_lists_every_element_in_hex() {
+        let theme = Theme {
+            background: 0x0086,
+            border: 0x318C,
+            title: 0x7FFF,
+            text: 0x6318,
+            health: 0x7C00,
+            mana: 0x001F,
+            gold: GOLD,
+            combat: COMBAT,
+        };
+        assert_eq!(
+            theme.dump(),
+            [
+                "background: 0x0086",
+                "border: 0x318C",
+                "title: 0x7FFF",
+                "text: 0x6318",
+                "health: 0x7C00",
+                "mana: 0x001F",
+                "gold: 0x7FE0",
+                "combat: 0x7D08",
+            ]
+        );
+    }
+
+    #[test]
+    fn lerp_blends_each_channel() {
+        let a = irgb(0, 10, 31);
+        let b = irgb(31, 10, 0);
+        assert_eq!(lerp_irgb(a, b, 0.0), a);
+        assert_eq!(lerp_irgb(a, b, 1.0), b);
+        assert_eq!(lerp_irgb(a, b, 0.5), irgb(16, 10, 16));
+        assert_eq!(lerp_irgb(a, b, 2.0), b);
+    }
+
+    #[test]
+    fn theme_lerp_reaches_combat_theme() {
+        let base = Theme {
+            background: PANEL_BG,
+            border: PANEL_BORDER,
+            title: 0x7FFF,
+            text: 0x6318,
+            health: 0x7C00,
+            mana: 0x001F,
+            gold: GOLD,
+            combat: COMBAT,
+        };
+        let combat = base.combat();
+        assert_eq!(base.lerp(&combat, 0.0), base);
+        assert_eq!(base.lerp(&combat, 1.0), combat);
+        assert_eq!(combat.text, base.text);
+        assert_ne!(combat.border, base.border);
+    }
+
+    #[test]
+    fn hsv_primaries() {
+        assert_eq!(hsv_to_irgb(0.0, 1.0, 1.0), irgb(31, 0, 0));
+        assert_eq!(hsv_to_irgb(120.0, 1.0, 1.0), irgb(0, 31, 0));
+        assert_eq!(hsv_to_irgb(240.0, 1.0, 1.0), irgb(0, 0, 31));
+    }
+
+    #[test]
+    fn hsv_secondaries_and_wrap() {
+        assert_eq!(hsv_to_irgb(60.0, 1.0, 1.0), irgb(31, 31, 0));
+        assert_eq!(hsv_to_irgb(180.0, 1.0, 1.0), irgb(0, 31, 31));
+        assert_eq!(hsv_to_irgb(300.0, 1.0, 1.0), irgb(31, 0, 31));
+        assert_eq!(hsv_to_irgb(360.0, 1.0, 1.0), irgb(31, 0, 0));
+        assert_eq!(hsv_to_irgb(-120.0, 1.0, 1.0), irgb(0, 0, 31));
+    }
+
+    #[test]
+    fn hsv_saturation_and_value() {
+        assert_eq!(hsv_to_irgb(200.0, 0.0, 1.0), irgb(31, 31, 31));
+        assert_eq!(hsv_to_irgb(200.0, 1.0, 0.0), 0);
+        assert_eq!(hsv_to_irgb(0.0, 1.0, 0.5), irgb(16, 0, 0));
+    }
+}
@@ -0,0 +1,208 @@
+//! Rolling "am I in combat?" heuristic.
+//!
+//! HP is sampled once per tick. Any drop in HP enters combat; combat is
+//! cleared once HP has gone [`COMBAT_HOLD_TICKS`] ticks without dropping
+//! again. Healing does not end combat early.
+//!
+//! Outside combat the HP gained since the last drop gives a regeneration
+//! rate, and from it an estimate of when HP will be full. No estimate is
+//! made during combat, where incoming damage makes the rate meaningless.
+
+use std::sync::Mutex;
+
+use crate::TICKS_PER_SECOND;
+
+/// Ticks without an HP drop before combat is considered over (5 seconds).
+pub const COMBAT_HOLD_TICKS: u32 = 5 * TICKS_PER_SECOND;
+
+#[derive(Debug)]
+pub struct CombatTracker {
+    last_hp: Option<i32>,
+    /// Ticks since the last HP drop, `None` if no drop has been seen.
+    ticks_since_drop: Option<u32>,
+    /// HP at the start of the current stretch without a drop, and ticks
+    /// since then.
+    climb: Option<(i32, u32)>,
+}
+
+impl CombatTracker {
+    pub const fn new() -> Self {
+        Self { last_hp: None, ticks_since_drop: None, climb: None }
+    }
+
+    /// Feeds one tick's HP value into the state machine.
+    pub fn sample(&mut self, hp: i32) {
+        let dropped = matches!(self.last_hp, Some(last) if hp < last);
+        self.last_hp = Some(hp);
+        self.climb = match self.climb {
+            Some((from, ticks)) if !dropped => Some((from, ticks.saturating_add(1))),
+            _ => Some((hp, 0)),
+        };
+
+        self.ticks_since_drop = if dropped {
+            Some(0)
+        } else {
+            self.ticks_since_drop.map(|t| t.saturating_add(1))
+        };
+    }
+
+    pub fn in_combat(&self) -> bool {
+        matches!(self.ticks_since_drop, Some(t) if t < COMBAT_HOLD_TICKS)
+    }
+
+    /// Ticks until HP reaches `max_hp` at the rate gained since the last
+    /// drop. `None` in combat, at full HP, or while HP is not rising.
+    pub fn ticks_to_full(&self, max_hp: i32) -> Option<u32> {
+        if self.in_combat() {
+            return None;
+        }
+        let (hp, (from, ticks)) = (self.last_hp?, self.climb?);
+        let gained = hp - from;
+        if hp >= max_hp || gained <= 0 || ticks == 0 {
+            return None;
+        }
+        let missing = (max_hp - hp) as u64;
+        let gained = gained as u64;
+        Some(((missing * ticks as u64 + gained - 1) / gained).min(u32::MAX as u64) as u32)
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+static TRACKER: Mutex<CombatTracker> = Mutex::new(CombatTracker::new());
+
+pub fn with_tracker<R>(f: impl FnOnce(&mut CombatTracker) -> R) -> R {
+    let mut guard = TRACKER.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut guard)
+}
+
+/// Samples the current HP; call once per tick.
+pub fn sample(hp: i32) {
+    with_tracker(|t| t.sample(hp));
+}
+
+/// Forgets all history, e.g. when a new game session starts.
+pub fn reset() {
+    with_tracker(|t| t.reset());
+}
+
+/// Returns true while the player has recently lost HP.
+pub fn in_combat() -> bool {
+    with_tracker(|t| t.in_combat())
+}
+
+/// Seconds until HP is full at the current regeneration rate, rounded up.
+/// `None` during combat; see [`CombatTracker::ticks_to_full`].
+pub fn secs_to_full(max_hp: i32) -> Option<u32> {
+    with_tracker(|t| t.ticks_to_full(max_hp)).map(|ticks| (ticks + TICKS_PER_SECOND - 1) / TICKS_PER_SECOND)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(t: &mut CombatTracker, samples: &[i32]) {
+        for &hp in samples {
+            t.sample(hp);
+        }
+    }
+
+    #[test]
+    fn starts_out_of_combat() {
+        let mut t = CombatTracker::new();
+        assert!(!t.in_combat());
+        feed(&mut t, &[100, 100, 100]);
+        assert!(!t.in_combat());
+    }
+
+    #[test]
+    fn first_sample_is_not_a_drop() {
+        let mut t = CombatTracker::new();
+        t.sample(10);
+        assert!(!t.in_combat());
+    }
+
+    #[test]
+    fn hp_drop_enters_combat() {
+        let mut t = CombatTracker::new();
+        feed(&mut t, &[100, 90]);
+        assert!(t.in_combat());
+    }
+
+    #[test]
+    fn healing_does_not_enter_combat() {
+        let mut t = CombatTracker::new();
+        feed(&mut t, &[50, 60, 70]);
+        assert!(!t.in_combat());
+    }
+
+    #[test]
+    fn clears_after_hold_ticks_without_drop() {
+        let mut t = CombatTracker::new();
+        feed(&mut t, &[100, 90]);
+        for _ in 0..COMBAT_HOLD_TICKS - 1 {
+            t.sample(90);
+            assert!(t.in_combat());
+        }
+        t.sample(90);
+        assert!(!t.in_combat());
+    }
+
+    #[test]
+    fn new_drop_restarts_hold() {
+        let mut t = CombatTracker::new();
+        feed(&mut t, &[100, 90]);
+        for _ in 0..COMBAT_HOLD_TICKS - 1 {
+            t.sample(90);
+        }
+        t.sample(80);
+        for _ in 0..COMBAT_HOLD_TICKS - 1 {
+            t.sample(85);
+            assert!(t.in_combat());
+        }
+        t.sample(85);
+        assert!(!t.in_combat());
+    }
+
+    #[test]
+    fn regen_is_estimated_from_the_climb_since_the_last_drop() {
+        let mut t = CombatTracker::new();
+        feed(&mut t, &[100, 50]);
+        // 2 HP per tick once combat has cleared.
+        for i in 1..=COMBAT_HOLD_TICKS as i32 {
+            t.sample(50 + 2 * i);
+        }
+        let hp = 50 + 2 * COMBAT_HOLD_TICKS as i32;
+        assert!(!t.in_combat());
+        assert_eq!(t.ticks_to_full(hp + 20), Some(10));
+        assert_eq!(t.ticks_to_full(hp + 21), Some(11));
+        assert_eq!(t.ticks_to_full(hp), None);
+    }
+
+    #[test]
+    fn regen_is_not_predicted_in_combat() {
+        let mut t = CombatTracker::new();
+        feed(&mut t, &[100, 50, 60, 70]);
+        assert!(t.in_combat());
+        assert_eq!(t.ticks_to_full(200), None);
+    }
+
+    #[test]
+    fn steady_hp_has_no_estimate() {
+        let mut t = CombatTracker::new();
+        feed(&mut t, &[80, 80, 80]);
+        assert_eq!(t.ticks_to_full(100), None);
+    }
+
+    #[test]
+    fn reset_clears_state() {
+        let mut t = CombatTracker::new();
+        feed(&mut t, &[100, 90]);
+        t.reset();
+        assert!(!t.in_combat());
+        t.sample(50);
+        assert!(!t.in_combat());
+    }
+}
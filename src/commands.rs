@@ -0,0 +1,184 @@
+//! Command names and prefix matching (`#config prefixmatch`).
+//!
+//! With prefix matching on, a command name that is not an exact match is
+//! run as the one known command it is a prefix of, so `#ov` runs `#overlay`.
+//! If several commands start with it, none runs and the candidates are
+//! listed instead.
+//!
+//! Commands the mod does not handle are passed back to the client. With
+//! `#config unknowncmd report`, `#` commands among them are also named in
+//! chat, which helps spot typos.
+
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+/// Every command name the dispatcher handles.
+pub const NAMES: &[&str] = &[
+    "#hello",
+    "#version",
+    "#stats",
+    "#missing",
+    "#idle",
+    "#ratios",
+    "#bits",
+    "#overlay",
+    "#statusbar",
+    "#grid",
+    "#mana",
+    "#timer",
+    "#mark",
+    "#preset",
+    "#goldtrack",
+    "#theme",
+    "#palette",
+    "#blinkspeed",
+    "#inputlat",
+    "#config",
+    "#benchmark",
+    "#frametimes",
+    "#clock",
+    "#fpsstats",
+    "#health",
+    "#timesource",
+    "#export",
+    "#bugreport",
+    #[cfg(feature = "debug")]
+    "#addr",
+];
+
+/// Resolve unknown command names by unique prefix.
+pub static PREFIX_MATCH: AtomicBool = AtomicBool::new(false);
+
+/// What to do about `#` commands the mod does not handle.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum UnknownCmd {
+    /// Pass them on without a word.
+    #[default]
+    Silent,
+    /// Pass them on, and say in chat that the mod did not know them.
+    Report,
+}
+
+impl UnknownCmd {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "silent" => Some(Self::Silent),
+            "report" => Some(Self::Report),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Silent => "silent",
+            Self::Report => "report",
+        }
+    }
+
+    fn from_u8(v: u8) -> Self {
+        match v {
+            1 => Self::Report,
+            _ => Self::Silent,
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::Silent => 0,
+            Self::Report => 1,
+        }
+    }
+}
+
+static UNKNOWN_CMD: AtomicU8 = AtomicU8::new(0);
+
+pub fn unknown_cmd() -> UnknownCmd {
+    UnknownCmd::from_u8(UNKNOWN_CMD.load(Ordering::Relaxed))
+}
+
+pub fn set_unknown_cmd(mode: UnknownCmd) {
+    UNKNOWN_CMD.store(mode.to_u8(), Ordering::Relaxed);
+}
+
+/// The chat line for command `name` that nothing handled, if `mode` wants
+/// one. Only `#` commands are reported; other text is the client's.
+pub fn unknown_notice(name: &str, mode: UnknownCmd) -> Option<String> {
+    (mode == UnknownCmd::Report && name.starts_with('#')).then(|| format!("Unknown command: {}", name))
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Resolved<'a> {
+    /// The name is a command, or a unique prefix of this one.
+    Command(&'a str),
+    /// Several commands start with the name.
+    Ambiguous(Vec<&'a str>),
+    /// Nothing matches; the command is not the mod's.
+    Unknown,
+}
+
+/// Looks `typed` up in `names`, falling back to a prefix search. A bare `#`
+/// is never treated as a prefix.
+pub fn resolve<'a>(typed: &str, names: &[&'a str]) -> Resolved<'a> {
+    if let Some(name) = names.iter().find(|n| **n == typed) {
+        return Resolved::Command(name);
+    }
+    if typed.len() <= 1 {
+        return Resolved::Unknown;
+    }
+    let matches: Vec<&str> = names.iter().copied().filter(|n| n.starts_with(typed)).collect();
+    match matches.as_slice() {
+        [] => Resolved::Unknown,
+        [one] => Resolved::Command(one),
+        _ => Resolved::Ambiguous(matches),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unique_prefix_resolves() {
+        assert_eq!(resolve("#ov", NAMES), Resolved::Command("#overlay"));
+        assert_eq!(resolve("#fr", NAMES), Resolved::Command("#frametimes"));
+    }
+
+    #[test]
+    fn exact_match_wins_over_longer_names() {
+        assert_eq!(resolve("#timer", NAMES), Resolved::Command("#timer"));
+        assert_eq!(resolve("#ab", &["#ab", "#abc"]), Resolved::Command("#ab"));
+    }
+
+    #[test]
+    fn ambiguous_prefix_lists_candidates() {
+        assert_eq!(resolve("#b", NAMES), Resolved::Ambiguous(vec!["#bits", "#blinkspeed", "#benchmark", "#bugreport"]));
+    }
+
+    #[test]
+    fn silent_mode_never_reports() {
+        assert_eq!(unknown_notice("#foo", UnknownCmd::Silent), None);
+        assert_eq!(unknown_notice("hello", UnknownCmd::Silent), None);
+    }
+
+    #[test]
+    fn report_mode_names_hash_commands_only() {
+        assert_eq!(unknown_notice("#foo", UnknownCmd::Report).as_deref(), Some("Unknown command: #foo"));
+        assert_eq!(unknown_notice("hello", UnknownCmd::Report), None);
+        assert_eq!(unknown_notice("", UnknownCmd::Report), None);
+    }
+
+    #[test]
+    fn unknown_cmd_names_round_trip() {
+        for mode in [UnknownCmd::Silent, UnknownCmd::Report] {
+            assert_eq!(UnknownCmd::parse(mode.name()), Some(mode));
+            assert_eq!(UnknownCmd::from_u8(mode.to_u8()), mode);
+        }
+        assert_eq!(UnknownCmd::parse("loud"), None);
+    }
+
+    #[test]
+    fn no_match_is_unknown() {
+        assert_eq!(resolve("#zz", NAMES), Resolved::Unknown);
+        assert_eq!(resolve("#", NAMES), Resolved::Unknown);
+        assert_eq!(resolve("ov", NAMES), Resolved::Unknown);
+    }
+}
@@ -0,0 +1,773 @@
+//! Persistent mod settings stored in `rustmod.cfg`.
+//!
+//! The file is a plain list of `key = value` lines. Blank lines and lines
+//! starting with `#` are ignored. The same parser is used to load the file
+//! at startup and to validate it on demand via `#config check`. A
+//! `[preset <name>]` line starts a section holding that preset's settings;
+//! they are checked the same way but only applied by `#preset load`.
+//!
+//! Commands that change a persisted setting call [`mark_dirty`]; the file is
+//! rewritten from the live state in `amod_exit` only if something changed.
+//!
+//! The file lives in [`MOD_DIR`], which is created on first use. If that
+//! fails, or the file cannot be read or written, a single warning is logged
+//! and settings keep working in memory for the rest of the session.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::anim::{self, BlinkSpeed, Easing};
+use crate::{cmdlog, commands, grid, layout, lowhp, mana, menu, overlay, pager, statusbar};
+use crate::commands::UnknownCmd;
+use crate::overlay::StatLayout;
+use crate::presets::{self, Entries};
+use crate::render::Layer;
+use crate::{log, OVERLAY_X, OVERLAY_Y, SHOW_OVERLAY};
+
+/// Directory for the mod's files, relative to the client's working directory.
+pub const MOD_DIR: &str = "rustmod";
+
+/// Name of the settings file inside [`MOD_DIR`].
+pub const CONFIG_FILE: &str = "rustmod.cfg";
+
+/// Largest accepted overlay offset in pixels.
+pub const MAX_OFFSET: i32 = 4096;
+
+/// What the parser does with each successfully parsed line.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    /// Store parsed values into the live mod state.
+    Apply,
+    /// Only collect diagnostics; leave the live state untouched.
+    Validate,
+}
+
+/// A problem found on a single line of the config file.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Diagnostic {
+    /// The line is not of the form `key = value`.
+    Syntax { line: usize },
+    /// The key is not a known setting.
+    UnknownKey { line: usize, key: String },
+    /// The value could not be parsed for this key.
+    InvalidValue { line: usize, key: String, value: String },
+    /// The value parsed but lies outside the accepted range.
+    OutOfRange { line: usize, key: String, value: i32, min: i32, max: i32 },
+    /// A `[...]` line that is not `[preset <name>]` with a valid name.
+    UnknownSection { line: usize, header: String },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::Syntax { line } => write!(f, "line {}: expected 'key = value'", line),
+            Diagnostic::UnknownKey { line, key } => write!(f, "line {}: unknown key '{}'", line, key),
+            Diagnostic::InvalidValue { line, key, value } => {
+                write!(f, "line {}: invalid value '{}' for '{}'", line, value, key)
+            }
+            Diagnostic::OutOfRange { line, key, value, min, max } => {
+                write!(f, "line {}: '{}' = {} is out of range ({}..={})", line, key, value, min, max)
+            }
+            Diagnostic::UnknownSection { line, header } => write!(f, "line {}: unknown section '{}'", line, header),
+        }
+    }
+}
+
+/// A single parsed setting, ready to be applied.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Setting {
+    Overlay(bool),
+    OverlayX(i32),
+    OverlayY(i32),
+    OverlayAbs(Option<(i32, i32)>),
+    OverlayAnchor(Option<(i32, i32)>),
+    OverlayLines(u32),
+    OverlayVerbose(bool),
+    ZeroPad(i32),
+    Rainbow(bool),
+    CombatTheme(bool),
+    Grid(bool),
+    BlinkSpeed(BlinkSpeed),
+    Easing(Easing),
+    OverlayLayer(Layer),
+    OverlayBorder(bool),
+    OverlayMaxLines(i32),
+    TimerArcs(bool),
+    StatusBar(bool),
+    GridSpacing(i32),
+    DimBehindMenu(bool),
+    CombatLayout(StatLayout),
+    LowHpPercent(i32),
+    ManaThreshold(i32),
+    PrefixMatch(bool),
+    UnknownCmd(UnknownCmd),
+    CommandLog(bool),
+    PageSlide(bool),
+}
+
+impl Setting {
+    fn apply(self) {
+        match self {
+            Setting::Overlay(on) => SHOW_OVERLAY.store(on, Ordering::Relaxed),
+            Setting::OverlayX(x) => OVERLAY_X.store(x, Ordering::Relaxed),
+            Setting::OverlayY(y) => OVERLAY_Y.store(y, Ordering::Relaxed),
+            Setting::OverlayAbs(pos) => layout::set_absolute(pos),
+            Setting::OverlayAnchor(Some((x, y))) => layout::set_fraction(x, y),
+            Setting::OverlayAnchor(None) => layout::set_corner(),
+            Setting::OverlayLines(mask) => overlay::set_selected_lines(mask),
+            Setting::OverlayVerbose(on) => overlay::set_verbose(on),
+            Setting::ZeroPad(width) => overlay::set_zero_pad(width as usize),
+            Setting::Rainbow(on) => overlay::RAINBOW.store(on, Ordering::Relaxed),
+            Setting::CombatTheme(on) => overlay::COMBAT_THEME.store(on, Ordering::Relaxed),
+            Setting::Grid(on) => grid::SHOW.store(on, Ordering::Relaxed),
+            Setting::BlinkSpeed(speed) => anim::set_blink_speed(speed),
+            Setting::Easing(e) => anim::set_easing(e),
+            Setting::OverlayLayer(layer) => overlay::set_panel_layer(layer),
+            Setting::OverlayBorder(on) => overlay::BORDER.store(on, Ordering::Relaxed),
+            Setting::OverlayMaxLines(n) => overlay::set_max_lines(n as usize),
+            Setting::TimerArcs(on) => overlay::TIMER_ARCS.store(on, Ordering::Relaxed),
+            Setting::StatusBar(on) => statusbar::SHOW.store(on, Ordering::Relaxed),
+            Setting::GridSpacing(px) => grid::set_spacing(px),
+            Setting::DimBehindMenu(on) => menu::DIM.store(on, Ordering::Relaxed),
+            Setting::CombatLayout(layout) => overlay::set_stat_layout(layout),
+            Setting::LowHpPercent(pct) => lowhp::set_percent(pct),
+            Setting::ManaThreshold(t) => mana::set_threshold(t),
+            Setting::PrefixMatch(on) => commands::PREFIX_MATCH.store(on, Ordering::Relaxed),
+            Setting::UnknownCmd(mode) => commands::set_unknown_cmd(mode),
+            Setting::CommandLog(on) => cmdlog::ENABLED.store(on, Ordering::Relaxed),
+            Setting::PageSlide(on) => pager::SLIDE.store(on, Ordering::Relaxed),
+        }
+    }
+}
+
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "on" | "true" | "yes" | "1" => Some(true),
+        "off" | "false" | "no" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn invalid(line: usize, key: &str, value: &str) -> Diagnostic {
+    Diagnostic::InvalidValue { line, key: key.to_string(), value: value.to_string() }
+}
+
+fn parse_int(line: usize, key: &str, value: &str, min: i32, max: i32) -> Result<i32, Diagnostic> {
+    let n: i32 = value.parse().map_err(|_| invalid(line, key, value))?;
+    if n < min || n > max {
+        return Err(Diagnostic::OutOfRange { line, key: key.to_string(), value: n, min, max });
+    }
+    Ok(n)
+}
+
+/// `off`, or `x,y` in pixels.
+fn parse_abs(line: usize, key: &str, value: &str) -> Result<Option<(i32, i32)>, Diagnostic> {
+    if value == "off" {
+        return Ok(None);
+    }
+    let (x, y) = value.split_once(',').ok_or_else(|| invalid(line, key, value))?;
+    Ok(Some((parse_int(line, key, x.trim(), 0, MAX_OFFSET)?, parse_int(line, key, y.trim(), 0, MAX_OFFSET)?)))
+}
+
+/// `corner`, or `x,y` in percent of the screen.
+fn parse_anchor(line: usize, key: &str, value: &str) -> Result<Option<(i32, i32)>, Diagnostic> {
+    if value == "corner" {
+        return Ok(None);
+    }
+    let (x, y) = value.split_once(',').ok_or_else(|| invalid(line, key, value))?;
+    Ok(Some((parse_int(line, key, x.trim(), 0, 100)?, parse_int(line, key, y.trim(), 0, 100)?)))
+}
+
+/// Parses one line. Returns `Ok(None)` for blank lines and comments.
+fn parse_line(line: usize, text: &str) -> Result<Option<Setting>, Diagnostic> {
+    let text = text.trim();
+    if text.is_empty() || text.starts_with('#') {
+        return Ok(None);
+    }
+
+    let (key, value) = text.split_once('=').ok_or(Diagnostic::Syntax { line })?;
+    let key = key.trim();
+    let value = value.trim();
+    if key.is_empty() {
+        return Err(Diagnostic::Syntax { line });
+    }
+
+    let setting = match key {
+        "overlay" => Setting::Overlay(parse_bool(value).ok_or_else(|| invalid(line, key, value))?),
+        "overlay_x" => Setting::OverlayX(parse_int(line, key, value, 0, MAX_OFFSET)?),
+        "overlay_y" => Setting::OverlayY(parse_int(line, key, value, 0, MAX_OFFSET)?),
+        "overlay_abs" => Setting::OverlayAbs(parse_abs(line, key, value)?),
+        "overlay_anchor" => Setting::OverlayAnchor(parse_anchor(line, key, value)?),
+        "overlay_lines" => Setting::OverlayLines(overlay::line::parse(value).ok_or_else(|| invalid(line, key, value))?),
+        "overlay_verbose" => Setting::OverlayVerbose(parse_bool(value).ok_or_else(|| invalid(line, key, value))?),
+        "zero_pad" => Setting::ZeroPad(parse_int(line, key, value, 0, overlay::MAX_ZERO_PAD as i32)?),
+        "rainbow" => Setting::Rainbow(parse_bool(value).ok_or_else(|| invalid(line, key, value))?),
+        "combat_theme" => Setting::CombatTheme(parse_bool(value).ok_or_else(|| invalid(line, key, value))?),
+        "grid" => Setting::Grid(parse_bool(value).ok_or_else(|| invalid(line, key, value))?),
+        "blink_speed" => Setting::BlinkSpeed(BlinkSpeed::parse(value).ok_or_else(|| invalid(line, key, value))?),
+        "easing" => Setting::Easing(Easing::parse(value).ok_or_else(|| invalid(line, key, value))?),
+        "overlay_border" => Setting::OverlayBorder(parse_bool(value).ok_or_else(|| invalid(line, key, value))?),
+        "overlay_max_lines" => {
+            let (min, max) = (overlay::MIN_MAX_LINES as i32, overlay::MAX_MAX_LINES as i32);
+            Setting::OverlayMaxLines(parse_int(line, key, value, min, max)?)
+        }
+        "timer_arcs" => Setting::TimerArcs(parse_bool(value).ok_or_else(|| invalid(line, key, value))?),
+        "status_bar" => Setting::StatusBar(parse_bool(value).ok_or_else(|| invalid(line, key, value))?),
+        "grid_spacing" => Setting::GridSpacing(parse_int(line, key, value, grid::MIN_SPACING, grid::MAX_SPACING)?),
+        "dim_behind_menu" => Setting::DimBehindMenu(parse_bool(value).ok_or_else(|| invalid(line, key, value))?),
+        "overlay_layer" => Setting::OverlayLayer(Layer::parse(value).ok_or_else(|| invalid(line, key, value))?),
+        "combat_layout" => Setting::CombatLayout(StatLayout::parse(value).ok_or_else(|| invalid(line, key, value))?),
+        "low_hp_percent" => Setting::LowHpPercent(parse_int(line, key, value, 0, 100)?),
+        "mana_threshold" => Setting::ManaThreshold(parse_int(line, key, value, 0, mana::MAX_THRESHOLD)?),
+        "prefix_match" => Setting::PrefixMatch(parse_bool(value).ok_or_else(|| invalid(line, key, value))?),
+        "unknown_cmd" => Setting::UnknownCmd(UnknownCmd::parse(value).ok_or_else(|| invalid(line, key, value))?),
+        "command_log" => Setting::CommandLog(parse_bool(value).ok_or_else(|| invalid(line, key, value))?),
+        "page_slide" => Setting::PageSlide(parse_bool(value).ok_or_else(|| invalid(line, key, value))?),
+        _ => return Err(Diagnostic::UnknownKey { line, key: key.to_string() }),
+    };
+    Ok(Some(setting))
+}
+
+/// Which part of the file a line belongs to.
+enum Section {
+    /// The live settings before any section header.
+    Live,
+    /// Settings of the preset with this index in the parsed list.
+    Preset(usize),
+    /// Lines under a bad header, which are skipped.
+    Unknown,
+}
+
+/// The preset name in a `[preset <name>]` header.
+fn preset_header(header: &str) -> Option<&str> {
+    let name = header.strip_prefix('[')?.strip_suffix(']')?.trim().strip_prefix("preset ")?.trim();
+    presets::valid_name(name).then_some(name)
+}
+
+/// Parses `text`, applying live settings in [`Mode::Apply`] only, and
+/// returns the diagnostics and every preset section's valid lines.
+fn parse_sections(text: &str, mode: Mode) -> (Vec<Diagnostic>, Vec<(String, Entries)>) {
+    let mut diagnostics = Vec::new();
+    let mut found: Vec<(String, Entries)> = Vec::new();
+    let mut section = Section::Live;
+    for (idx, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            section = match preset_header(trimmed) {
+                Some(name) => {
+                    found.push((name.to_string(), Vec::new()));
+                    Section::Preset(found.len() - 1)
+                }
+                None => {
+                    diagnostics.push(Diagnostic::UnknownSection { line: idx + 1, header: trimmed.to_string() });
+                    Section::Unknown
+                }
+            };
+            continue;
+        }
+        match (parse_line(idx + 1, line), &section) {
+            (Ok(Some(setting)), Section::Live) if mode == Mode::Apply => setting.apply(),
+            (Ok(Some(_)), Section::Preset(i)) => {
+                if let Some((key, value)) = trimmed.split_once('=') {
+                    found[*i].1.push((key.trim().to_string(), value.trim().to_string()));
+                }
+            }
+            (Ok(_), _) => {}
+            (Err(_), Section::Unknown) => {}
+            (Err(d), _) => diagnostics.push(d),
+        }
+    }
+    (diagnostics, found)
+}
+
+/// Runs the parser over `text`. In [`Mode::Apply`] every valid line is applied
+/// even if other lines have problems, and the presets found replace the
+/// stored ones; in [`Mode::Validate`] nothing is applied.
+pub fn parse(text: &str, mode: Mode) -> Vec<Diagnostic> {
+    let (diagnostics, found) = parse_sections(text, mode);
+    if mode == Mode::Apply {
+        presets::with_presets(|p| {
+            p.clear();
+            for (name, entries) in found {
+                p.set(&name, entries);
+            }
+        });
+    }
+    diagnostics
+}
+
+/// The live value of each setting in `keys`, for a preset.
+pub fn snapshot(keys: &[&str]) -> Entries {
+    entries()
+        .into_iter()
+        .filter(|(key, _)| keys.contains(key))
+        .map(|(key, value)| (key.to_string(), value))
+        .collect()
+}
+
+/// Applies preset `entries`, skipping any that no longer parse. Returns how
+/// many were applied.
+pub fn apply_entries(entries: &Entries) -> usize {
+    entries
+        .iter()
+        .filter_map(|(key, value)| parse_line(0, &format!("{} = {}", key, value)).ok().flatten())
+        .map(Setting::apply)
+        .count()
+}
+
+/// File operations used by [`Store`], so tests can run without a disk.
+pub trait Fs: Send {
+    fn is_dir(&self, path: &Path) -> bool;
+    fn create_dir_all(&mut self, path: &Path) -> io::Result<()>;
+    fn read_to_string(&self, path: &Path) -> io::Result<String>;
+    fn write(&mut self, path: &Path, text: &str) -> io::Result<()>;
+    /// Adds `text` to the end of the file, creating it if needed.
+    fn append(&mut self, path: &Path, text: &str) -> io::Result<()>;
+}
+
+/// The real filesystem.
+pub struct DiskFs;
+
+impl Fs for DiskFs {
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn create_dir_all(&mut self, path: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(path)
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+
+    fn write(&mut self, path: &Path, text: &str) -> io::Result<()> {
+        std::fs::write(path, text)
+    }
+
+    fn append(&mut self, path: &Path, text: &str) -> io::Result<()> {
+        use std::io::Write;
+        std::fs::OpenOptions::new().create(true).append(true).open(path)?.write_all(text.as_bytes())
+    }
+}
+
+/// Where the settings file is read from and written to.
+pub struct Store {
+    dir: PathBuf,
+    fs: Box<dyn Fs>,
+    /// Set once the directory or file proved unusable; nothing touches the
+    /// disk after that.
+    in_memory: bool,
+}
+
+impl Store {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self::with_fs(dir, DiskFs)
+    }
+
+    pub fn with_fs(dir: impl AsRef<Path>, fs: impl Fs + 'static) -> Self {
+        Self { dir: dir.as_ref().to_path_buf(), fs: Box::new(fs), in_memory: false }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(CONFIG_FILE)
+    }
+
+    pub fn is_in_memory(&self) -> bool {
+        self.in_memory
+    }
+
+    /// Switches to in-memory operation, warning the first time only.
+    fn fail(&mut self, what: &str, err: io::Error) {
+        if !self.in_memory {
+            self.in_memory = true;
+            log(&format!(
+                "Rust Demo Mod: {} {} ({}); settings will not be saved this session",
+                what,
+                self.dir.display(),
+                err
+            ));
+        }
+    }
+
+    /// Makes sure the directory exists. Returns false when running in memory.
+    fn prepare(&mut self) -> bool {
+        if self.in_memory {
+            return false;
+        }
+        if self.fs.is_dir(&self.dir) {
+            return true;
+        }
+        match self.fs.create_dir_all(&self.dir) {
+            Ok(()) => true,
+            Err(err) => {
+                self.fail("cannot create", err);
+                false
+            }
+        }
+    }
+
+    /// Reads and applies the settings file. A missing file is not an error.
+    pub fn load(&mut self) -> Vec<Diagnostic> {
+        if !self.prepare() {
+            return Vec::new();
+        }
+        match self.fs.read_to_string(&self.path()) {
+            Ok(text) => parse(&text, Mode::Apply),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => {
+                self.fail("cannot read settings in", err);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Writes `text` as the settings file, unless running in memory.
+    pub fn save(&mut self, text: &str) {
+        if !self.prepare() {
+            return;
+        }
+        if let Err(err) = self.fs.write(&self.path(), text) {
+            self.fail("cannot write settings to", err);
+        }
+    }
+
+    /// Writes `text` to file `name` next to the settings and returns its
+    /// path. Fails without touching the filesystem when running in memory.
+    pub fn write_file(&mut self, name: &str, text: &str) -> io::Result<PathBuf> {
+        let path = self.file_path(name)?;
+        self.fs.write(&path, text)?;
+        Ok(path)
+    }
+
+    /// Like [`Store::write_file`], but adds `text` to the end of the file.
+    pub fn append_file(&mut self, name: &str, text: &str) -> io::Result<PathBuf> {
+        let path = self.file_path(name)?;
+        self.fs.append(&path, text)?;
+        Ok(path)
+    }
+
+    fn file_path(&mut self, name: &str) -> io::Result<PathBuf> {
+        if !self.prepare() {
+            let reason = "settings directory unusable, nothing is written this session";
+            return Err(io::Error::new(io::ErrorKind::Other, reason));
+        }
+        Ok(self.dir.join(name))
+    }
+}
+
+static STORE: Mutex<Option<Store>> = Mutex::new(None);
+
+fn with_store<R>(f: impl FnOnce(&mut Store) -> R) -> R {
+    let mut guard = STORE.lock().unwrap_or_else(|e| e.into_inner());
+    f(guard.get_or_insert_with(|| Store::new(MOD_DIR)))
+}
+
+/// Replaces the settings store, e.g. with one backed by a mock filesystem.
+#[cfg(test)]
+pub fn set_store(store: Store) {
+    *STORE.lock().unwrap_or_else(|e| e.into_inner()) = Some(store);
+}
+
+pub fn config_path() -> PathBuf {
+    with_store(|s| s.path())
+}
+
+/// Reads the settings file as it is on disk, without applying it.
+pub fn read_config() -> io::Result<String> {
+    with_store(|s| s.fs.read_to_string(&s.path()))
+}
+
+/// Directory the mod keeps its files in.
+pub fn mod_dir() -> PathBuf {
+    with_store(|s| s.dir.clone())
+}
+
+/// True once the settings directory proved unusable this session.
+pub fn is_in_memory() -> bool {
+    with_store(|s| s.is_in_memory())
+}
+
+/// Writes file `name` into the mod directory; see [`Store::write_file`].
+pub fn write_file(name: &str, text: &str) -> io::Result<PathBuf> {
+    with_store(|s| s.write_file(name, text))
+}
+
+/// Appends to file `name` in the mod directory; see [`Store::append_file`].
+pub fn append_file(name: &str, text: &str) -> io::Result<PathBuf> {
+    with_store(|s| s.append_file(name, text))
+}
+
+/// Loads and applies the settings file.
+pub fn load() -> Vec<Diagnostic> {
+    with_store(Store::load)
+}
+
+pub fn on_off(on: bool) -> &'static str {
+    if on {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+/// The live value of every persisted setting, in file order.
+fn entries() -> Vec<(&'static str, String)> {
+    vec![
+        ("overlay", on_off(SHOW_OVERLAY.load(Ordering::Relaxed)).to_string()),
+        ("overlay_x", OVERLAY_X.load(Ordering::Relaxed).to_string()),
+        ("overlay_y", OVERLAY_Y.load(Ordering::Relaxed).to_string()),
+        ("overlay_abs", layout::absolute().map_or("off".to_string(), |(x, y)| format!("{},{}", x, y))),
+        ("overlay_anchor", layout::fraction().map_or("corner".to_string(), |(x, y)| format!("{},{}", x, y))),
+        ("overlay_lines", overlay::line::names(overlay::selected_lines())),
+        ("overlay_verbose", on_off(overlay::verbose()).to_string()),
+        ("zero_pad", overlay::zero_pad().to_string()),
+        ("rainbow", on_off(overlay::RAINBOW.load(Ordering::Relaxed)).to_string()),
+        ("combat_theme", on_off(overlay::COMBAT_THEME.load(Ordering::Relaxed)).to_string()),
+        ("grid", on_off(grid::SHOW.load(Ordering::Relaxed)).to_string()),
+        ("blink_speed", anim::blink_speed().name().to_string()),
+        ("easing", anim::easing().name().to_string()),
+        ("overlay_border", on_off(overlay::BORDER.load(Ordering::Relaxed)).to_string()),
+        ("overlay_max_lines", overlay::max_lines().to_string()),
+        ("timer_arcs", on_off(overlay::TIMER_ARCS.load(Ordering::Relaxed)).to_string()),
+        ("status_bar", on_off(statusbar::SHOW.load(Ordering::Relaxed)).to_string()),
+        ("grid_spacing", grid::spacing().to_string()),
+        ("overlay_layer", overlay::panel_layer().name().to_string()),
+        ("dim_behind_menu", on_off(menu::DIM.load(Ordering::Relaxed)).to_string()),
+        ("combat_layout", overlay::stat_layout().name().to_string()),
+        ("low_hp_percent", lowhp::percent().to_string()),
+        ("mana_threshold", mana::raw_threshold().to_string()),
+        ("prefix_match", on_off(commands::PREFIX_MATCH.load(Ordering::Relaxed)).to_string()),
+        ("unknown_cmd", commands::unknown_cmd().name().to_string()),
+        ("command_log", on_off(cmdlog::ENABLED.load(Ordering::Relaxed)).to_string()),
+        ("page_slide", on_off(pager::SLIDE.load(Ordering::Relaxed)).to_string()),
+    ]
+}
+
+/// Renders the live settings in the file format read by [`parse`].
+pub fn serialize() -> String {
+    let mut out = String::from("# Rust Demo Mod settings\n");
+    for (key, value) in entries() {
+        out.push_str(&format!("{} = {}\n", key, value));
+    }
+    presets::with_presets(|p| {
+        for (name, entries) in p.iter() {
+            out.push_str(&format!("\n[preset {}]\n", name));
+            for (key, value) in entries {
+                out.push_str(&format!("{} = {}\n", key, value));
+            }
+        }
+    });
+    out
+}
+
+static DIRTY: AtomicBool = AtomicBool::new(false);
+
+/// Records that a persisted setting changed and the file needs rewriting.
+pub fn mark_dirty() {
+    DIRTY.store(true, Ordering::Relaxed);
+}
+
+/// True if a persisted setting changed since the file was last read or
+/// written.
+pub fn is_dirty() -> bool {
+    DIRTY.load(Ordering::Relaxed)
+}
+
+/// Forgets unsaved changes and a previous session's disk failure, so the
+/// next [`load`] starts over.
+pub fn reset() {
+    DIRTY.store(false, Ordering::Relaxed);
+    with_store(|s| s.in_memory = false);
+}
+
+/// Writes the live settings to disk if anything changed since loading.
+///
+/// Runs synchronously from `amod_exit`; a failure is reported through the
+/// client log.
+pub fn save_if_dirty() {
+    if DIRTY.swap(false, Ordering::Relaxed) {
+        let text = serialize();
+        with_store(|s| s.save(&text));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_file_has_no_diagnostics() {
+        let text = "# comment\n\noverlay = on\noverlay_x = 20\n  overlay_y=30  \n";
+        assert!(parse(text, Mode::Validate).is_empty());
+    }
+
+    #[test]
+    fn collects_every_problem_with_line_numbers() {
+        let text = "overlay = on\nbogus = 1\noverlay_x = 99999\nnot a setting\noverlay = maybe\noverlay_y = abc\n";
+        let diags = parse(text, Mode::Validate);
+        assert_eq!(
+            diags,
+            vec![
+                Diagnostic::UnknownKey { line: 2, key: "bogus".into() },
+                Diagnostic::OutOfRange { line: 3, key: "overlay_x".into(), value: 99999, min: 0, max: MAX_OFFSET },
+                Diagnostic::Syntax { line: 4 },
+                Diagnostic::InvalidValue { line: 5, key: "overlay".into(), value: "maybe".into() },
+                Diagnostic::InvalidValue { line: 6, key: "overlay_y".into(), value: "abc".into() },
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_key_is_a_syntax_error() {
+        assert_eq!(parse(" = on", Mode::Validate), vec![Diagnostic::Syntax { line: 1 }]);
+    }
+
+    #[test]
+    fn validate_mode_does_not_apply() {
+        let before = OVERLAY_X.load(Ordering::Relaxed);
+        parse("overlay_x = 1234", Mode::Validate);
+        assert_eq!(OVERLAY_X.load(Ordering::Relaxed), before);
+    }
+
+    #[test]
+    fn serialized_settings_parse_cleanly() {
+        let text = serialize();
+        assert!(text.contains("blink_speed = "));
+        assert!(parse(&text, Mode::Validate).is_empty());
+    }
+
+    #[test]
+    fn absolute_position_is_off_or_a_pair() {
+        assert_eq!(parse_line(1, "overlay_abs = off"), Ok(Some(Setting::OverlayAbs(None))));
+        assert_eq!(parse_line(1, "overlay_abs = 120, 40"), Ok(Some(Setting::OverlayAbs(Some((120, 40))))));
+        assert_eq!(
+            parse("overlay_abs = 120\noverlay_abs = 5,99999", Mode::Validate),
+            vec![
+                Diagnostic::InvalidValue { line: 1, key: "overlay_abs".into(), value: "120".into() },
+                Diagnostic::OutOfRange { line: 2, key: "overlay_abs".into(), value: 99999, min: 0, max: MAX_OFFSET },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_blink_speed() {
+        assert_eq!(
+            parse("blink_speed = ludicrous", Mode::Validate),
+            vec![Diagnostic::InvalidValue { line: 1, key: "blink_speed".into(), value: "ludicrous".into() }]
+        );
+    }
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rustmod-test-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn creates_missing_directory_and_saves() {
+        let dir = scratch_dir("create");
+        let mut store = Store::new(dir.join("nested"));
+        assert!(store.load().is_empty());
+        store.save("overlay = on\n");
+        assert!(!store.is_in_memory());
+        assert_eq!(std::fs::read_to_string(store.path()).unwrap(), "overlay = on\n");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn other_files_are_written_beside_the_settings() {
+        let fs = crate::testing::MemFs::default();
+        let mut store = Store::with_fs("mem", fs.clone());
+        let path = store.write_file("history.csv", "tick\n").unwrap();
+        assert_eq!(path, Path::new("mem").join("history.csv"));
+        assert_eq!(fs.file(&path).as_deref(), Some("tick\n"));
+
+        let mut store = Store::with_fs("mem", crate::testing::MemFs::read_only());
+        let err = store.write_file("history.csv", "tick\n").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        // A failed export says so, but is no reason to stop saving settings.
+        assert!(!store.is_in_memory());
+    }
+
+    #[test]
+    fn appends_add_to_the_end_of_the_file() {
+        let fs = crate::testing::MemFs::default();
+        let mut store = Store::with_fs("mem", fs.clone());
+        store.append_file("commands.log", "a\n").unwrap();
+        let path = store.append_file("commands.log", "b\n").unwrap();
+        assert_eq!(fs.file(&path).as_deref(), Some("a\nb\n"));
+    }
+
+    #[test]
+    fn nothing_is_written_in_memory() {
+        let fs = crate::testing::MemFs::default();
+        let mut store = Store::with_fs("mem", fs.clone());
+        store.in_memory = true;
+        assert!(store.write_file("bugreport.txt", "report").is_err());
+        assert_eq!(fs.file(&Path::new("mem").join("bugreport.txt")), None);
+        assert!(store.append_file("commands.log", "0 tick 0: #hello\n").is_err());
+        assert_eq!(fs.file(&Path::new("mem").join("commands.log")), None);
+    }
+
+    #[test]
+    fn unusable_directory_falls_back_to_memory() {
+        let dir = scratch_dir("blocked");
+        std::fs::create_dir_all(&dir).unwrap();
+        // A regular file where the directory should be can never be created
+        // or written into, even when running as root.
+        let blocker = dir.join("not-a-dir");
+        std::fs::write(&blocker, "").unwrap();
+
+        let mut store = Store::new(&blocker);
+        assert!(store.load().is_empty());
+        assert!(store.is_in_memory());
+        store.save("overlay = on\n");
+        store.save("overlay = off\n");
+        assert_eq!(std::fs::read_to_string(&blocker).unwrap(), "");
+
+        let warnings: Vec<String> = crate::testing::take_log();
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        assert!(warnings[0].contains("settings will not be saved"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn preset_sections_are_collected_not_applied() {
+        let text = "[preset farming]\noverlay_x = 40\nstatus_bar = on\n\n[ preset raid-2 ]\noverlay_y = 5\n";
+        let (diags, found) = parse_sections(text, Mode::Apply);
+        assert!(diags.is_empty(), "{:?}", diags);
+        let pair = |k: &str, v: &str| (k.to_string(), v.to_string());
+        assert_eq!(
+            found,
+            [
+                ("farming".to_string(), vec![pair("overlay_x", "40"), pair("status_bar", "on")]),
+                ("raid-2".to_string(), vec![pair("overlay_y", "5")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn preset_lines_are_checked_and_bad_sections_skipped() {
+        let text = "[preset farming]\noverlay_x = abc\noverlay_y = 3\n[presets]\nbogus = 1\n[preset two words]\n";
+        let (diags, found) = parse_sections(text, Mode::Validate);
+        assert_eq!(
+            diags,
+            vec![
+                Diagnostic::InvalidValue { line: 2, key: "overlay_x".into(), value: "abc".into() },
+                Diagnostic::UnknownSection { line: 4, header: "[presets]".into() },
+                Diagnostic::UnknownSection { line: 6, header: "[preset two words]".into() },
+            ]
+        );
+        assert_eq!(found, [("farming".to_string(), vec![("overlay_y".to_string(), "3".to_string())])]);
+    }
+
+    #[test]
+    fn diagnostics_render_readably() {
+        let d = Diagnostic::OutOfRange { line: 3, key: "overlay_x".into(), value: -1, min: 0, max: 10 };
+        assert_eq!(d.to_string(), "line 3: 'overlay_x' = -1 is out of range (0..=10)");
+    }
+}
@@ -0,0 +1,95 @@
+//! CSV export of the vitals history (`#export csv`).
+
+use std::path::PathBuf;
+
+use crate::config;
+use crate::history::{self, Sample};
+
+/// Name of the export file inside the mod directory.
+pub const CSV_FILE: &str = "history.csv";
+
+/// Largest export written, in bytes. The oldest rows are dropped to fit.
+pub const MAX_CSV_BYTES: usize = 1024 * 1024;
+
+const HEADER: &str = "tick,hp,mana,gold,exp\n";
+
+/// Result of rendering samples as CSV.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Csv {
+    pub text: String,
+    pub rows: usize,
+    /// Rows left out because the size cap was reached.
+    pub dropped: usize,
+}
+
+/// Renders `samples` as CSV, oldest first. Rows are kept from the newest
+/// back; once a row would push the text past `max_bytes`, it and every
+/// older row are dropped, so the export always ends at the latest sample.
+pub fn to_csv<'a>(samples: impl IntoIterator<Item = &'a Sample>, max_bytes: usize) -> Csv {
+    let samples: Vec<&Sample> = samples.into_iter().collect();
+    let mut rows = Vec::new();
+    let mut len = HEADER.len();
+    for s in samples.iter().rev() {
+        let row = format!("{},{},{},{},{}\n", s.tick, s.hp, s.mana, s.gold, s.exp);
+        if len + row.len() > max_bytes {
+            break;
+        }
+        len += row.len();
+        rows.push(row);
+    }
+    let mut text = String::with_capacity(len);
+    text.push_str(HEADER);
+    for row in rows.iter().rev() {
+        text.push_str(row);
+    }
+    Csv { text, rows: rows.len(), dropped: samples.len() - rows.len() }
+}
+
+/// Writes the current history to [`CSV_FILE`]. Returns the path and the
+/// rendered CSV on success.
+pub fn write_history() -> std::io::Result<(PathBuf, Csv)> {
+    let csv = history::with_history(|h| to_csv(h.samples(), MAX_CSV_BYTES));
+    let path = config::write_file(CSV_FILE, &csv.text)?;
+    Ok((path, csv))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(tick: u32) -> Sample {
+        Sample { tick, hp: 100, mana: -5, gold: 1234, exp: 99 }
+    }
+
+    #[test]
+    fn empty_history_is_just_header() {
+        let csv = to_csv(&[], MAX_CSV_BYTES);
+        assert_eq!(csv, Csv { text: HEADER.to_string(), rows: 0, dropped: 0 });
+    }
+
+    #[test]
+    fn writes_one_row_per_sample() {
+        let samples = [sample(24), sample(48)];
+        let csv = to_csv(&samples, MAX_CSV_BYTES);
+        assert_eq!(csv.text, "tick,hp,mana,gold,exp\n24,100,-5,1234,99\n48,100,-5,1234,99\n");
+        assert_eq!(csv.rows, 2);
+    }
+
+    #[test]
+    fn size_cap_keeps_the_newest_rows() {
+        let samples: Vec<Sample> = (0..10).map(sample).collect();
+        let row_len = "0,100,-5,1234,99\n".len();
+        let csv = to_csv(&samples, HEADER.len() + 3 * row_len);
+        assert_eq!(csv.rows, 3);
+        assert_eq!(csv.dropped, 7);
+        assert_eq!(csv.text, "tick,hp,mana,gold,exp\n7,100,-5,1234,99\n8,100,-5,1234,99\n9,100,-5,1234,99\n");
+    }
+
+    #[test]
+    fn rows_before_the_cap_stay_dropped() {
+        let short = Sample { tick: 1, hp: 0, mana: 0, gold: 0, exp: 0 };
+        let long = Sample { tick: 2, hp: 1_000_000, mana: 0, gold: 0, exp: 0 };
+        let csv = to_csv(&[short, long], HEADER.len() + "1,0,0,0,0\n".len());
+        assert_eq!((csv.rows, csv.dropped), (0, 2));
+    }
+}
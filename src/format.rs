@@ -0,0 +1,200 @@
+//! Number formatting for chat and overlay text.
+
+use std::ffi::c_int;
+
+/// Formats `n` with `,` between groups of three digits, e.g. `-1,234,567`.
+/// Uses the unsigned magnitude, so `c_int::MIN` cannot overflow.
+pub fn format_thousands(n: c_int) -> String {
+    let digits = n.unsigned_abs().to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+    if n < 0 {
+        out.push('-');
+    }
+    let head = match digits.len() % 3 {
+        0 => 3,
+        r => r,
+    };
+    out.push_str(&digits[..head]);
+    for group in digits.as_bytes()[head..].chunks(3) {
+        out.push(',');
+        out.push_str(std::str::from_utf8(group).unwrap_or_default());
+    }
+    out
+}
+
+/// Formats `n` with a `k`/`M`/`B` suffix, keeping one decimal below ten
+/// units, e.g. `950`, `7.2k`, `72k`, `1.4M`. Safe for the full `i64` range.
+pub fn format_compact(n: i64) -> String {
+    const UNITS: [(u64, &str); 3] = [(1_000_000_000, "B"), (1_000_000, "M"), (1_000, "k")];
+
+    let sign = if n < 0 { "-" } else { "" };
+    let abs = n.unsigned_abs();
+    for (size, suffix) in UNITS {
+        if abs >= size {
+            let tenths = abs / (size / 10);
+            return if tenths < 100 {
+                format!("{}{}.{}{}", sign, tenths / 10, tenths % 10, suffix)
+            } else {
+                format!("{}{}{}", sign, abs / size, suffix)
+            };
+        }
+    }
+    format!("{}{}", sign, abs)
+}
+
+/// Formats `n` padded with leading zeros to at least `width` characters,
+/// counting the sign, e.g. `00500` or `-0042`. Wider numbers are unchanged.
+pub fn format_padded(n: c_int, width: usize) -> String {
+    format!("{:0width$}", n, width = width)
+}
+
+/// Formats a duration as `MM:SS`; minutes grow past two digits if needed.
+pub fn format_mmss(seconds: u32) -> String {
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Breaks `text` into lines of at most `width` characters, at spaces where
+/// possible. Words longer than `width` are split.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split(' ').filter(|w| !w.is_empty()) {
+        let mut word: Vec<char> = word.chars().collect();
+        let line_len = line.chars().count();
+        if line_len > 0 && line_len + 1 + word.len() <= width {
+            line.push(' ');
+            line.extend(&word);
+            continue;
+        }
+        if line_len > 0 {
+            lines.push(std::mem::take(&mut line));
+        }
+        while word.len() > width {
+            lines.push(word.drain(..width).collect());
+        }
+        line.extend(word);
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Formats the 32 bits of `n`, most significant first, in nibble groups,
+/// e.g. `0000 0000 0000 0000 0000 0000 0000 0101`.
+pub fn format_bits(n: c_int) -> String {
+    let bits = format!("{:032b}", n as u32);
+    bits.as_bytes()
+        .chunks(4)
+        .map(|nibble| std::str::from_utf8(nibble).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Indices of the set bits in `n`, lowest first.
+pub fn set_bits(n: c_int) -> Vec<u32> {
+    (0..32).filter(|bit| (n as u32) & (1 << bit) != 0).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thousands_groups_digits() {
+        assert_eq!(format_thousands(0), "0");
+        assert_eq!(format_thousands(999), "999");
+        assert_eq!(format_thousands(1000), "1,000");
+        assert_eq!(format_thousands(123456), "123,456");
+        assert_eq!(format_thousands(1234567), "1,234,567");
+    }
+
+    #[test]
+    fn thousands_keeps_sign() {
+        assert_eq!(format_thousands(-1), "-1");
+        assert_eq!(format_thousands(-1234), "-1,234");
+    }
+
+    #[test]
+    fn thousands_handles_extremes() {
+        assert_eq!(format_thousands(c_int::MAX), "2,147,483,647");
+        assert_eq!(format_thousands(c_int::MIN), "-2,147,483,648");
+    }
+
+    #[test]
+    fn compact_small_numbers_unchanged() {
+        assert_eq!(format_compact(0), "0");
+        assert_eq!(format_compact(999), "999");
+        assert_eq!(format_compact(-42), "-42");
+    }
+
+    #[test]
+    fn compact_uses_suffixes() {
+        assert_eq!(format_compact(1000), "1.0k");
+        assert_eq!(format_compact(7250), "7.2k");
+        assert_eq!(format_compact(72_000), "72k");
+        assert_eq!(format_compact(999_999), "999k");
+        assert_eq!(format_compact(1_400_000), "1.4M");
+        assert_eq!(format_compact(12_000_000_000), "12B");
+        assert_eq!(format_compact(-72_000), "-72k");
+    }
+
+    #[test]
+    fn compact_handles_extremes() {
+        assert_eq!(format_compact(c_int::MAX.into()), "2.1B");
+        assert_eq!(format_compact(c_int::MIN.into()), "-2.1B");
+        assert_eq!(format_compact(i64::MAX), "9223372036B");
+        assert_eq!(format_compact(i64::MIN), "-9223372036B");
+    }
+
+    #[test]
+    fn bits_grouped_by_nibble() {
+        assert_eq!(format_bits(5), "0000 0000 0000 0000 0000 0000 0000 0101");
+        assert_eq!(format_bits(-1), "1111 1111 1111 1111 1111 1111 1111 1111");
+        assert_eq!(format_bits(c_int::MIN), "1000 0000 0000 0000 0000 0000 0000 0000");
+    }
+
+    #[test]
+    fn lists_set_bits() {
+        assert_eq!(set_bits(0), Vec::<u32>::new());
+        assert_eq!(set_bits(0b1010_0001), [0, 5, 7]);
+        assert_eq!(set_bits(c_int::MIN), [31]);
+    }
+
+    #[test]
+    fn wrap_breaks_at_spaces() {
+        assert_eq!(wrap("overlay = on", 40), ["overlay = on"]);
+        assert_eq!(wrap("one two three four", 9), ["one two", "three", "four"]);
+        assert_eq!(wrap("", 10), [""]);
+    }
+
+    #[test]
+    fn wrap_splits_long_words() {
+        assert_eq!(wrap("abcdefghij xy", 4), ["abcd", "efgh", "ij", "xy"]);
+        assert_eq!(wrap("ab abcdefgh", 4), ["ab", "abcd", "efgh"]);
+    }
+
+    #[test]
+    fn padded_puts_sign_before_zeros() {
+        assert_eq!(format_padded(500, 5), "00500");
+        assert_eq!(format_padded(-42, 5), "-0042");
+        assert_eq!(format_padded(0, 3), "000");
+        assert_eq!(format_padded(0, 0), "0");
+    }
+
+    #[test]
+    fn padded_leaves_wide_numbers_alone() {
+        assert_eq!(format_padded(123456, 3), "123456");
+        assert_eq!(format_padded(-5, 2), "-5");
+        assert_eq!(format_padded(c_int::MIN, 4), "-2147483648");
+    }
+
+    #[test]
+    fn mmss_pads_and_grows() {
+        assert_eq!(format_mmss(0), "00:00");
+        assert_eq!(format_mmss(75), "01:15");
+        assert_eq!(format_mmss(3599), "59:59");
+        assert_eq!(format_mmss(6000), "100:00");
+    }
+}
@@ -0,0 +1,126 @@
+//! Session FPS aggregates (`#fpsstats`).
+//!
+//! Once per second of ticks, the frames drawn during that second are taken
+//! as one FPS sample. The game starts at any point within a second, so
+//! counting begins at the first second boundary after it and that partial
+//! second is never sampled. The lowest, highest and mean sample since the
+//! game started are kept, and reported by `#fpsstats` and when the client
+//! exits.
+
+use std::sync::Mutex;
+
+use crate::format::format_mmss;
+
+#[derive(Debug, Default)]
+pub struct FpsStats {
+    started: bool,
+    /// Frame count at the last second boundary, or `None` before the first
+    /// boundary after the game started.
+    base: Option<u32>,
+    samples: u32,
+    min: u32,
+    max: u32,
+    total: u64,
+}
+
+impl FpsStats {
+    pub const fn new() -> Self {
+        Self { started: false, base: None, samples: 0, min: 0, max: 0, total: 0 }
+    }
+
+    /// Starts over for a game that has just started.
+    pub fn start(&mut self) {
+        *self = Self { started: true, ..Self::new() };
+    }
+
+    /// Takes the sample for the second ending at frame count `frames`.
+    /// Ignored before the game starts; the first call after it only marks
+    /// where the first full second begins.
+    pub fn sample(&mut self, frames: u32) {
+        if !self.started {
+            return;
+        }
+        let Some(base) = self.base.replace(frames) else {
+            return;
+        };
+        let fps = frames.wrapping_sub(base);
+        if self.samples == 0 {
+            (self.min, self.max) = (fps, fps);
+        } else {
+            self.min = self.min.min(fps);
+            self.max = self.max.max(fps);
+        }
+        self.samples += 1;
+        self.total += fps as u64;
+    }
+
+    pub fn summary(&self) -> String {
+        if !self.started {
+            return "FPS: no game started yet".to_string();
+        }
+        if self.samples == 0 {
+            return "FPS: no samples yet, the first full second is still running".to_string();
+        }
+        format!(
+            "FPS over {}: min {}  avg {:.1}  max {}",
+            format_mmss(self.samples),
+            self.min,
+            self.total as f64 / self.samples as f64,
+            self.max
+        )
+    }
+}
+
+static STATS: Mutex<FpsStats> = Mutex::new(FpsStats::new());
+
+pub fn with_stats<R>(f: impl FnOnce(&mut FpsStats) -> R) -> R {
+    let mut guard = STATS.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut guard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nothing_is_sampled_before_the_game_starts() {
+        let mut stats = FpsStats::new();
+        stats.sample(500);
+        assert_eq!(stats.summary(), "FPS: no game started yet");
+        stats.start();
+        stats.sample(560);
+        assert!(stats.summary().contains("no samples yet"));
+    }
+
+    #[test]
+    fn aggregates_per_second_samples() {
+        let mut stats = FpsStats::new();
+        stats.start();
+        for frames in [1000, 1060, 1100, 1161] {
+            stats.sample(frames);
+        }
+        assert_eq!(stats.summary(), "FPS over 00:03: min 40  avg 53.7  max 61");
+    }
+
+    #[test]
+    fn the_partial_second_after_starting_is_not_sampled() {
+        let mut stats = FpsStats::new();
+        // Started a few frames before a second boundary at frame 1005.
+        stats.start();
+        stats.sample(1005);
+        stats.sample(1065);
+        assert_eq!(stats.summary(), "FPS over 00:01: min 60  avg 60.0  max 60");
+    }
+
+    #[test]
+    fn starting_again_discards_the_last_game() {
+        let mut stats = FpsStats::new();
+        stats.start();
+        stats.sample(0);
+        stats.sample(5);
+        stats.start();
+        stats.sample(100);
+        stats.sample(160);
+        assert_eq!(stats.summary(), "FPS over 00:01: min 60  avg 60.0  max 60");
+    }
+}
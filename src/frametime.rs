@@ -0,0 +1,130 @@
+//! Frame time percentiles (`#frametimes`).
+//!
+//! The time between consecutive `amod_frame` calls is kept for the last
+//! [`WINDOW`] frames. Percentiles are computed on demand by sorting a copy
+//! of the window, so nothing is spent on them while drawing.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::ring::RingBuffer;
+
+/// Number of frame times kept.
+pub const WINDOW: usize = 600;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Percentiles {
+    pub samples: usize,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+/// Nearest-rank percentile `p` (1..=100) of an ascending, non-empty slice.
+pub fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    let rank = ((p * sorted.len() + 99) / 100).max(1);
+    sorted[rank - 1]
+}
+
+#[derive(Debug, Default)]
+pub struct FrameTimes {
+    last: Option<Instant>,
+    samples: RingBuffer<Duration, WINDOW>,
+}
+
+impl FrameTimes {
+    pub const fn new() -> Self {
+        Self { last: None, samples: RingBuffer::new() }
+    }
+
+    /// Records a frame starting at `now`.
+    pub fn frame(&mut self, now: Instant) {
+        if let Some(last) = self.last.replace(now) {
+            self.samples.push(now.saturating_duration_since(last));
+        }
+    }
+
+    pub fn percentiles(&self) -> Option<Percentiles> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        Some(Percentiles {
+            samples: sorted.len(),
+            p50: percentile(&sorted, 50),
+            p95: percentile(&sorted, 95),
+            p99: percentile(&sorted, 99),
+        })
+    }
+}
+
+static FRAME_TIMES: Mutex<FrameTimes> = Mutex::new(FrameTimes::new());
+
+pub fn with_frame_times<R>(f: impl FnOnce(&mut FrameTimes) -> R) -> R {
+    let mut guard = FRAME_TIMES.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut guard)
+}
+
+pub fn frame() {
+    with_frame_times(|f| f.frame(Instant::now()));
+}
+
+pub fn reset() {
+    with_frame_times(|f| *f = FrameTimes::new());
+}
+
+pub fn percentiles() -> Option<Percentiles> {
+    with_frame_times(|f| f.percentiles())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn nearest_rank_on_one_to_hundred() {
+        let sorted: Vec<Duration> = (1..=100).map(ms).collect();
+        assert_eq!(percentile(&sorted, 50), ms(50));
+        assert_eq!(percentile(&sorted, 95), ms(95));
+        assert_eq!(percentile(&sorted, 99), ms(99));
+    }
+
+    #[test]
+    fn small_sets_pick_existing_samples() {
+        let sorted = [ms(10), ms(20), ms(30)];
+        assert_eq!(percentile(&sorted, 50), ms(20));
+        assert_eq!(percentile(&sorted, 99), ms(30));
+        assert_eq!(percentile(&[ms(7)], 1), ms(7));
+    }
+
+    #[test]
+    fn p99_needs_more_than_one_percent_hitches() {
+        let mut f = FrameTimes::new();
+        let start = Instant::now();
+        let mut t = start;
+        f.frame(t);
+        for i in 0..100 {
+            t += if i == 50 { ms(200) } else { ms(16) };
+            f.frame(t);
+        }
+        let p = f.percentiles().unwrap();
+        assert_eq!(p.samples, 100);
+        assert_eq!((p.p50, p.p95), (ms(16), ms(16)));
+        assert_eq!(p.p99, ms(16));
+        t += ms(200);
+        f.frame(t);
+        assert_eq!(f.percentiles().unwrap().p99, ms(200));
+    }
+
+    #[test]
+    fn first_frame_has_no_duration() {
+        let mut f = FrameTimes::new();
+        f.frame(Instant::now());
+        assert_eq!(f.percentiles(), None);
+    }
+}
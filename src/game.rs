@@ -0,0 +1,130 @@
+//! Safe accessors for the client's game state globals.
+//!
+//! The client owns these globals and only writes them from the main thread,
+//! which is also the thread all mod callbacks run on.
+
+use std::ffi::{c_int, CStr};
+
+use crate::{V_HP, V_MANA, V_MAX};
+
+pub fn hp() -> c_int {
+    unsafe { crate::hp }
+}
+
+pub fn mana() -> c_int {
+    unsafe { crate::mana }
+}
+
+pub fn gold() -> c_int {
+    unsafe { crate::gold }
+}
+
+pub fn experience() -> c_int {
+    unsafe { crate::experience }
+}
+
+/// Current value of stat slot `idx`, or 0 if the index is out of range.
+pub fn stat(idx: usize) -> c_int {
+    if idx < V_MAX {
+        unsafe { crate::value[0][idx] }
+    } else {
+        0
+    }
+}
+
+pub fn max_hp() -> c_int {
+    stat(V_HP)
+}
+
+pub fn max_mana() -> c_int {
+    stat(V_MANA)
+}
+
+/// Highest level taken as a real answer from `exp2level`; anything above
+/// it, or below 0, is treated as garbage.
+pub const MAX_SANE_LEVEL: c_int = 999;
+
+pub fn level() -> c_int {
+    level_for(experience())
+}
+
+/// The current level, or `None` if the client's answer is out of range.
+pub fn try_level() -> Option<c_int> {
+    try_level_for(experience())
+}
+
+fn raw_level_for(xp: c_int) -> c_int {
+    unsafe { crate::exp2level(xp) }
+}
+
+/// Level the client assigns to `xp` experience, clamped to the sane range.
+pub fn level_for(xp: c_int) -> c_int {
+    raw_level_for(xp).clamp(0, MAX_SANE_LEVEL)
+}
+
+/// Level the client assigns to `xp` experience, or `None` if it is out of
+/// the sane range, for callers that must not act on a bogus level.
+pub fn try_level_for(xp: c_int) -> Option<c_int> {
+    let level = raw_level_for(xp);
+    (0..=MAX_SANE_LEVEL).contains(&level).then_some(level)
+}
+
+pub fn username() -> String {
+    unsafe {
+        let cstr = CStr::from_ptr(crate::username.as_ptr());
+        cstr.to_string_lossy().into_owned()
+    }
+}
+
+/// How far `current` is below `max`; never negative.
+pub fn missing(current: c_int, max: c_int) -> c_int {
+    max.saturating_sub(current).max(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn sane_levels_pass_through() {
+        testing::set_exp2level(|xp| xp / 100);
+        assert_eq!(try_level_for(0), Some(0));
+        assert_eq!(try_level_for(4_250), Some(42));
+        assert_eq!(try_level_for(99_900), Some(MAX_SANE_LEVEL));
+        assert_eq!(level_for(4_250), 42);
+    }
+
+    #[test]
+    fn garbage_levels_are_rejected() {
+        testing::set_exp2level(|xp| if xp > 1_000 { c_int::MAX } else { -xp });
+        assert_eq!(try_level_for(5_000), None);
+        assert_eq!(try_level_for(3), None);
+        testing::set_exp2level(|_| MAX_SANE_LEVEL + 1);
+        assert_eq!(try_level_for(0), None);
+    }
+
+    #[test]
+    fn garbage_levels_are_clamped() {
+        testing::set_exp2level(|xp| if xp > 1_000 { c_int::MAX } else { -xp });
+        assert_eq!(level_for(5_000), MAX_SANE_LEVEL);
+        assert_eq!(level_for(3), 0);
+    }
+
+    #[test]
+    fn missing_is_difference_to_max() {
+        assert_eq!(missing(400, 500), 100);
+        assert_eq!(missing(500, 500), 0);
+    }
+
+    #[test]
+    fn missing_never_negative() {
+        assert_eq!(missing(600, 500), 0);
+        assert_eq!(missing(10, -5), 0);
+    }
+
+    #[test]
+    fn missing_does_not_overflow() {
+        assert_eq!(missing(c_int::MIN, c_int::MAX), c_int::MAX);
+    }
+}
@@ -0,0 +1,119 @@
+//! Gold income tracking.
+//!
+//! `#goldtrack on` remembers the current gold and tick; from then on the
+//! overlay shows the average gold per minute since that point and projects
+//! it over a full day. The first minute is treated as a warm-up because a
+//! single loot pickup would otherwise give absurd projections.
+
+use std::ffi::c_int;
+use std::sync::Mutex;
+
+use crate::TICKS_PER_SECOND;
+
+/// Ticks of tracking required before a rate is reported (one minute).
+pub const WARMUP_TICKS: u32 = 60 * TICKS_PER_SECOND;
+
+const TICKS_PER_MINUTE: i64 = 60 * TICKS_PER_SECOND as i64;
+const TICKS_PER_DAY: i64 = 24 * 60 * TICKS_PER_MINUTE;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Start {
+    tick: u32,
+    gold: c_int,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Rate {
+    /// Not enough time has passed for a meaningful rate.
+    WarmingUp,
+    /// Gold per minute and projected gold per day since tracking started.
+    Earning { per_minute: f64, per_day: i64 },
+    /// Gold has gone down since tracking started.
+    Losing { per_minute: f64 },
+}
+
+/// Average rate between a starting sample and now.
+fn rate_between(start_tick: u32, start_gold: c_int, now_tick: u32, now_gold: c_int) -> Rate {
+    let elapsed = now_tick.wrapping_sub(start_tick);
+    if elapsed < WARMUP_TICKS {
+        return Rate::WarmingUp;
+    }
+
+    let delta = now_gold as i64 - start_gold as i64;
+    let per_minute = delta as f64 * TICKS_PER_MINUTE as f64 / elapsed as f64;
+    if delta < 0 {
+        Rate::Losing { per_minute }
+    } else {
+        Rate::Earning { per_minute, per_day: delta * TICKS_PER_DAY / elapsed as i64 }
+    }
+}
+
+static START: Mutex<Option<Start>> = Mutex::new(None);
+
+fn with_start<R>(f: impl FnOnce(&mut Option<Start>) -> R) -> R {
+    let mut guard = START.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut guard)
+}
+
+/// Starts (or restarts) tracking from the given tick and gold amount.
+pub fn start(tick: u32, gold: c_int) {
+    with_start(|s| *s = Some(Start { tick, gold }));
+}
+
+pub fn stop() {
+    with_start(|s| *s = None);
+}
+
+/// Current rate, or `None` if tracking is off.
+pub fn rate(now_tick: u32, now_gold: c_int) -> Option<Rate> {
+    let start = with_start(|s| *s)?;
+    Some(rate_between(start.tick, start.gold, now_tick, now_gold))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: u32 = TICKS_PER_MINUTE as u32;
+
+    #[test]
+    fn warming_up_before_first_minute() {
+        assert_eq!(rate_between(0, 0, WARMUP_TICKS - 1, 1000), Rate::WarmingUp);
+    }
+
+    #[test]
+    fn projects_per_minute_over_a_day() {
+        match rate_between(100, 1000, 100 + 2 * MINUTE, 1100) {
+            Rate::Earning { per_minute, per_day } => {
+                assert_eq!(per_minute, 50.0);
+                assert_eq!(per_day, 50 * 60 * 24);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn no_change_is_zero_earning() {
+        assert_eq!(rate_between(0, 500, MINUTE, 500), Rate::Earning { per_minute: 0.0, per_day: 0 });
+    }
+
+    #[test]
+    fn decrease_is_losing() {
+        assert_eq!(rate_between(0, 500, MINUTE, 400), Rate::Losing { per_minute: -100.0 });
+    }
+
+    #[test]
+    fn survives_tick_wraparound() {
+        let start = u32::MAX - 10;
+        let now = start.wrapping_add(MINUTE);
+        assert_eq!(rate_between(start, 0, now, 60), Rate::Earning { per_minute: 60.0, per_day: 60 * 60 * 24 });
+    }
+
+    #[test]
+    fn extreme_gold_does_not_overflow() {
+        match rate_between(0, c_int::MIN, MINUTE, c_int::MAX) {
+            Rate::Earning { per_day, .. } => assert_eq!(per_day, (u32::MAX as i64) * 60 * 24),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
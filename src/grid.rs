@@ -0,0 +1,82 @@
+//! Alignment grid for placing elements (`#grid`).
+//!
+//! Faint lines every `spacing` pixels from the top-left corner dot to the
+//! bottom-right one, on the background layer so everything else draws over
+//! them. Every few lines are labelled with their offset from the corner,
+//! which is what `overlay_x` and `overlay_y` measure.
+
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+use crate::colors::{GRID, GRID_LABEL};
+use crate::layout::Screen;
+use crate::render::{DrawList, Layer, TextFlags};
+
+pub const DEFAULT_SPACING: i32 = 32;
+pub const MIN_SPACING: i32 = 8;
+pub const MAX_SPACING: i32 = 512;
+
+/// Every this many lines get a coordinate label.
+const LABEL_EVERY: usize = 4;
+
+/// Show the grid (`#grid`).
+pub static SHOW: AtomicBool = AtomicBool::new(false);
+
+static SPACING: AtomicI32 = AtomicI32::new(DEFAULT_SPACING);
+
+pub fn spacing() -> i32 {
+    SPACING.load(Ordering::Relaxed)
+}
+
+/// Sets the line spacing. `px` must be in `MIN_SPACING..=MAX_SPACING`.
+pub fn set_spacing(px: i32) {
+    SPACING.store(px, Ordering::Relaxed);
+}
+
+pub fn reset() {
+    SHOW.store(false, Ordering::Relaxed);
+    set_spacing(DEFAULT_SPACING);
+}
+
+/// Offsets of the lines between `0` and `extent`, `spacing` apart. The edge
+/// at 0 is left out: the screen border marks it already.
+fn offsets(extent: i32, spacing: i32) -> impl Iterator<Item = i32> {
+    (1..).map(move |i| i * spacing).take_while(move |&d| d < extent)
+}
+
+/// Records the grid across `screen` on the background layer, if shown.
+/// Leaves the list on the standard layer.
+pub fn build(list: &mut DrawList, screen: Screen) {
+    if !SHOW.load(Ordering::Relaxed) {
+        return;
+    }
+    let spacing = spacing();
+    list.set_layer(Layer::Background);
+    for (i, dx) in offsets(screen.width(), spacing).enumerate() {
+        let x = screen.left + dx;
+        list.line(x, screen.top, x, screen.bottom, GRID);
+        if (i + 1) % LABEL_EVERY == 0 {
+            list.text(x + 2, screen.top + 2, GRID_LABEL, TextFlags::SMALL, &dx.to_string());
+        }
+    }
+    for (i, dy) in offsets(screen.height(), spacing).enumerate() {
+        let y = screen.top + dy;
+        list.line(screen.left, y, screen.right, y, GRID);
+        if (i + 1) % LABEL_EVERY == 0 {
+            list.text(screen.left + 2, y + 2, GRID_LABEL, TextFlags::SMALL, &dy.to_string());
+        }
+    }
+    list.set_layer(Layer::Standard);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets_stop_before_the_far_edge() {
+        assert_eq!(offsets(100, 32).collect::<Vec<_>>(), [32, 64, 96]);
+        assert_eq!(offsets(96, 32).collect::<Vec<_>>(), [32, 64]);
+        assert_eq!(offsets(0, 32).count(), 0);
+        assert_eq!(offsets(-5, 32).count(), 0);
+    }
+}
@@ -0,0 +1,135 @@
+//! Frames-per-tick health check (`#health`).
+//!
+//! The client draws frames as fast as it can but ticks at a fixed rate, so
+//! frames per tick falling means the engine is struggling. Once per second
+//! the tick and frame counters are recorded; `#health` compares the live
+//! counters with the oldest record in the window and grades the result:
+//!
+//! - OK: at least 1 frame per tick (24 FPS and up)
+//! - degraded: at least 0.5 frames per tick (12-24 FPS)
+//! - stalling: fewer than 0.5 frames per tick (under 12 FPS)
+
+use std::sync::Mutex;
+
+use crate::ring::RingBuffer;
+use crate::TICKS_PER_SECOND;
+
+/// Seconds of counter records kept.
+pub const WINDOW_SECS: usize = 10;
+
+/// Frames per tick at or above which the client counts as healthy.
+pub const OK_FPT: f64 = 1.0;
+
+/// Frames per tick at or above which the client is degraded rather than
+/// stalling.
+pub const DEGRADED_FPT: f64 = 0.5;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Status {
+    Ok,
+    Degraded,
+    Stalling,
+}
+
+impl Status {
+    pub fn classify(frames_per_tick: f64) -> Self {
+        if frames_per_tick >= OK_FPT {
+            Self::Ok
+        } else if frames_per_tick >= DEGRADED_FPT {
+            Self::Degraded
+        } else {
+            Self::Stalling
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ok => "OK",
+            Self::Degraded => "degraded",
+            Self::Stalling => "stalling",
+        }
+    }
+}
+
+/// Tick and frame counters as `(ticks, frames)`, one record per second.
+#[derive(Debug, Default)]
+pub struct Window {
+    records: RingBuffer<(u32, u32), WINDOW_SECS>,
+}
+
+impl Window {
+    pub const fn new() -> Self {
+        Self { records: RingBuffer::new() }
+    }
+
+    pub fn record(&mut self, ticks: u32, frames: u32) {
+        self.records.push((ticks, frames));
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    /// The `#health` report for live counters `ticks` and `frames`.
+    pub fn report(&self, ticks: u32, frames: u32) -> Vec<String> {
+        let (t0, f0) = self.records.iter().next().copied().unwrap_or((0, 0));
+        let dt = ticks.wrapping_sub(t0);
+        let df = frames.wrapping_sub(f0);
+        if dt == 0 {
+            return vec!["Health: no ticks yet, nothing to measure".to_string()];
+        }
+        let per_tick = df as f64 / dt as f64;
+        vec![
+            format!("Health: {}", Status::classify(per_tick).name()),
+            format!(
+                "{} frames over {} ticks ({:.1}s): {:.2} frames per tick, {:.1} FPS",
+                df,
+                dt,
+                dt as f64 / TICKS_PER_SECOND as f64,
+                per_tick,
+                per_tick * TICKS_PER_SECOND as f64
+            ),
+        ]
+    }
+}
+
+static WINDOW: Mutex<Window> = Mutex::new(Window::new());
+
+pub fn with_window<R>(f: impl FnOnce(&mut Window) -> R) -> R {
+    let mut guard = WINDOW.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut guard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thresholds_are_inclusive() {
+        assert_eq!(Status::classify(2.5), Status::Ok);
+        assert_eq!(Status::classify(OK_FPT), Status::Ok);
+        assert_eq!(Status::classify(0.99), Status::Degraded);
+        assert_eq!(Status::classify(DEGRADED_FPT), Status::Degraded);
+        assert_eq!(Status::classify(0.1), Status::Stalling);
+    }
+
+    #[test]
+    fn measures_from_the_oldest_record() {
+        let mut window = Window::new();
+        for second in 0..=WINDOW_SECS as u32 {
+            // Fast for the first second only, which drops out of the window.
+            let frames = if second <= 1 { second * 240 } else { 240 + (second - 1) * 12 };
+            window.record(second * 24, frames);
+        }
+        let report = window.report(11 * 24, 240 + 10 * 12);
+        assert_eq!(report[0], "Health: degraded");
+        assert_eq!(report[1], "120 frames over 240 ticks (10.0s): 0.50 frames per tick, 12.0 FPS");
+    }
+
+    #[test]
+    fn reports_without_records() {
+        let window = Window::new();
+        assert_eq!(window.report(0, 50), ["Health: no ticks yet, nothing to measure"]);
+        assert_eq!(window.report(24, 6)[0], "Health: stalling");
+    }
+}
@@ -0,0 +1,111 @@
+//! Per-second history of the player's vitals.
+//!
+//! `amod_tick` offers a sample every tick, timestamped from the selected
+//! time source (`#timesource`); one is kept per [`SAMPLE_INTERVAL`], the
+//! first offered in it. Only the most recent [`CAPACITY`] samples are kept,
+//! and the history is cleared when a new game starts.
+
+use std::ffi::c_int;
+use std::sync::Mutex;
+
+use crate::ring::RingBuffer;
+use crate::TICKS_PER_SECOND;
+
+/// Ticks between samples (one second).
+pub const SAMPLE_INTERVAL: u32 = TICKS_PER_SECOND;
+
+/// Samples kept (one hour).
+pub const CAPACITY: usize = 3600;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Sample {
+    pub tick: u32,
+    pub hp: c_int,
+    pub mana: c_int,
+    pub gold: c_int,
+    pub exp: c_int,
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    samples: RingBuffer<Sample, CAPACITY>,
+    /// Interval of the last sample kept, as `tick / SAMPLE_INTERVAL`.
+    last_interval: Option<u32>,
+}
+
+impl History {
+    pub const fn new() -> Self {
+        Self { samples: RingBuffer::new(), last_interval: None }
+    }
+
+    pub fn push(&mut self, sample: Sample) {
+        self.samples.push(sample);
+    }
+
+    /// Keeps `sample` if it is the first offered in its interval. Wall-clock
+    /// time can skip ticks, so this does not wait for an exact multiple.
+    pub fn offer(&mut self, sample: Sample) {
+        let interval = sample.tick / SAMPLE_INTERVAL;
+        if self.last_interval != Some(interval) {
+            self.last_interval = Some(interval);
+            self.push(sample);
+        }
+    }
+
+    /// Samples from oldest to newest.
+    pub fn samples(&self) -> impl Iterator<Item = &Sample> {
+        self.samples.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.last_interval = None;
+    }
+}
+
+static HISTORY: Mutex<History> = Mutex::new(History::new());
+
+pub fn with_history<R>(f: impl FnOnce(&mut History) -> R) -> R {
+    let mut guard = HISTORY.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut guard)
+}
+
+/// Offers `sample` to the history; see [`History::offer`].
+pub fn record(sample: Sample) {
+    with_history(|h| h.offer(sample));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(tick: u32) -> Sample {
+        Sample { tick, hp: 1, mana: 2, gold: 3, exp: 4 }
+    }
+
+    #[test]
+    fn keeps_newest_samples_in_order() {
+        let mut h = History::new();
+        for t in 0..CAPACITY as u32 + 5 {
+            h.push(sample(t));
+        }
+        let ticks: Vec<u32> = h.samples().map(|s| s.tick).collect();
+        assert_eq!(ticks.len(), CAPACITY);
+        assert_eq!(ticks[0], 5);
+        assert_eq!(*ticks.last().unwrap(), CAPACITY as u32 + 4);
+    }
+
+    #[test]
+    fn keeps_the_first_sample_of_each_interval() {
+        let mut h = History::new();
+        // A wall clock that skips the exact boundary at 24.
+        for t in [1, 2, 23, 25, 30, 48, 49] {
+            h.offer(sample(t));
+        }
+        let ticks: Vec<u32> = h.samples().map(|s| s.tick).collect();
+        assert_eq!(ticks, [1, 25, 48]);
+        h.clear();
+        h.offer(sample(49));
+        assert_eq!(h.samples().count(), 1);
+    }
+}
@@ -0,0 +1,106 @@
+//! Time since each vital last changed (`#idle`).
+//!
+//! Every tick the current HP, mana, gold and experience are compared with
+//! the previous tick's, and the tick of the last change is kept for each.
+//! Tracking starts over when a game starts.
+
+use std::ffi::c_int;
+use std::sync::Mutex;
+
+use crate::format::format_mmss;
+use crate::history::Sample;
+use crate::TICKS_PER_SECOND;
+
+const NAMES: [&str; 4] = ["HP", "Mana", "Gold", "Exp"];
+
+fn values(s: &Sample) -> [c_int; 4] {
+    [s.hp, s.mana, s.gold, s.exp]
+}
+
+#[derive(Debug, Default)]
+pub struct Idle {
+    /// First tick observed.
+    since: Option<u32>,
+    last: [c_int; 4],
+    changed_at: [Option<u32>; 4],
+}
+
+impl Idle {
+    pub const fn new() -> Self {
+        Self { since: None, last: [0; 4], changed_at: [None; 4] }
+    }
+
+    pub fn observe(&mut self, sample: &Sample) {
+        let now = values(sample);
+        if self.since.is_none() {
+            self.since = Some(sample.tick);
+        } else {
+            for (i, (old, new)) in self.last.iter().zip(now).enumerate() {
+                if *old != new {
+                    self.changed_at[i] = Some(sample.tick);
+                }
+            }
+        }
+        self.last = now;
+    }
+
+    /// One line per vital as of tick `now`, or `None` before the first
+    /// observation.
+    pub fn lines(&self, now: u32) -> Option<Vec<String>> {
+        let since = self.since?;
+        let secs = |tick: u32| now.wrapping_sub(tick) / TICKS_PER_SECOND;
+        let lines = NAMES
+            .iter()
+            .zip(self.changed_at)
+            .map(|(name, changed)| match changed {
+                Some(tick) => format!("{} unchanged for {}", name, format_mmss(secs(tick))),
+                None => format!("{} unchanged since game start ({})", name, format_mmss(secs(since))),
+            })
+            .collect();
+        Some(lines)
+    }
+}
+
+static IDLE: Mutex<Idle> = Mutex::new(Idle::new());
+
+pub fn with_idle<R>(f: impl FnOnce(&mut Idle) -> R) -> R {
+    let mut guard = IDLE.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut guard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEC: u32 = TICKS_PER_SECOND;
+
+    fn sample(tick: u32, hp: c_int, gold: c_int) -> Sample {
+        Sample { tick, hp, mana: 10, gold, exp: 0 }
+    }
+
+    #[test]
+    fn nothing_to_report_before_the_first_tick() {
+        assert_eq!(Idle::new().lines(100), None);
+    }
+
+    #[test]
+    fn reports_time_since_each_change() {
+        let mut idle = Idle::new();
+        idle.observe(&sample(0, 100, 5));
+        idle.observe(&sample(SEC, 90, 5));
+        idle.observe(&sample(9 * SEC, 90, 7));
+        let lines = idle.lines(21 * SEC).unwrap();
+        assert_eq!(lines[0], "HP unchanged for 00:20");
+        assert_eq!(lines[2], "Gold unchanged for 00:12");
+    }
+
+    #[test]
+    fn values_that_never_changed_count_from_game_start() {
+        let mut idle = Idle::new();
+        idle.observe(&sample(48, 100, 5));
+        idle.observe(&sample(72, 100, 5));
+        let lines = idle.lines(48 + 30 * SEC).unwrap();
+        assert_eq!(lines[1], "Mana unchanged since game start (00:30)");
+        assert_eq!(lines[3], "Exp unchanged since game start (00:30)");
+    }
+}
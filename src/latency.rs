@@ -0,0 +1,154 @@
+//! Rough keypress-to-frame latency probe (`#inputlat`).
+//!
+//! A keypress is timestamped when `amod_keydown` sees it and the sample is
+//! completed when the next `amod_frame` finishes drawing. This only covers
+//! the time between the client handing the key to the mod and the mod's
+//! next frame; OS input queues, the client's own processing before calling
+//! the mod, and display latency are not included.
+
+use std::ffi::c_int;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::ring::RingBuffer;
+
+/// Number of samples in the rolling average.
+const WINDOW: usize = 16;
+
+/// Key code meaning "measure every key".
+pub const ANY_KEY: c_int = -1;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static KEY: AtomicI32 = AtomicI32::new(ANY_KEY);
+
+#[derive(Debug, Default)]
+pub struct Probe {
+    pending: Option<Instant>,
+    samples: RingBuffer<Duration, WINDOW>,
+}
+
+impl Probe {
+    pub const fn new() -> Self {
+        Self { pending: None, samples: RingBuffer::new() }
+    }
+
+    /// Starts a sample unless one is already waiting for its frame, so a
+    /// burst of keys measures from the first one.
+    pub fn press(&mut self, now: Instant) {
+        self.pending.get_or_insert(now);
+    }
+
+    /// Completes the pending sample, if any.
+    pub fn frame(&mut self, now: Instant) {
+        if let Some(start) = self.pending.take() {
+            self.samples.push(now.saturating_duration_since(start));
+        }
+    }
+
+    pub fn average(&self) -> Option<Duration> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        Some(self.samples.iter().sum::<Duration>() / self.samples.len() as u32)
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+static PROBE: Mutex<Probe> = Mutex::new(Probe::new());
+
+fn with_probe<R>(f: impl FnOnce(&mut Probe) -> R) -> R {
+    let mut guard = PROBE.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut guard)
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Turns the probe on for `key` (or [`ANY_KEY`]), discarding old samples.
+pub fn enable(key: c_int) {
+    with_probe(Probe::clear);
+    KEY.store(key, Ordering::Relaxed);
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn disable() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+/// Call from `amod_keydown`.
+pub fn key_down(key: c_int) {
+    let wanted = KEY.load(Ordering::Relaxed);
+    if is_enabled() && (wanted == ANY_KEY || wanted == key) {
+        with_probe(|p| p.press(Instant::now()));
+    }
+}
+
+/// Call at the end of `amod_frame`.
+pub fn frame_done() {
+    if is_enabled() {
+        with_probe(|p| p.frame(Instant::now()));
+    }
+}
+
+/// Rolling average latency, if the probe is on and has samples.
+pub fn average() -> Option<Duration> {
+    if !is_enabled() {
+        return None;
+    }
+    with_probe(|p| p.average())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn no_samples_no_average() {
+        let mut p = Probe::new();
+        assert_eq!(p.average(), None);
+        p.frame(Instant::now());
+        assert_eq!(p.average(), None);
+    }
+
+    #[test]
+    fn measures_press_to_next_frame() {
+        let t0 = Instant::now();
+        let mut p = Probe::new();
+        p.press(t0);
+        p.frame(t0 + ms(10));
+        p.frame(t0 + ms(50));
+        assert_eq!(p.average(), Some(ms(10)));
+    }
+
+    #[test]
+    fn burst_measures_from_first_key() {
+        let t0 = Instant::now();
+        let mut p = Probe::new();
+        p.press(t0);
+        p.press(t0 + ms(5));
+        p.frame(t0 + ms(8));
+        assert_eq!(p.average(), Some(ms(8)));
+    }
+
+    #[test]
+    fn average_rolls_over_window() {
+        let t0 = Instant::now();
+        let mut p = Probe::new();
+        p.press(t0);
+        p.frame(t0 + ms(1000));
+        for _ in 0..WINDOW {
+            p.press(t0);
+            p.frame(t0 + ms(4));
+        }
+        assert_eq!(p.average(), Some(ms(4)));
+    }
+}
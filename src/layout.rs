@@ -0,0 +1,291 @@
+//! Overlay placement on screen.
+//!
+//! The panel is positioned either by a pixel offset from the top-left screen
+//! corner (the default) or as a percentage of the screen size, which keeps it
+//! in the same relative spot regardless of resolution. An absolute pixel
+//! position, when set, overrides both until it is cleared.
+
+use std::ffi::c_int;
+use std::sync::atomic::{AtomicI32, AtomicU8, Ordering};
+use std::sync::Mutex;
+
+use crate::{dotx, doty, log, DOT_BR, DOT_TL, OVERLAY_X, OVERLAY_Y};
+
+const ANCHOR_CORNER: u8 = 0;
+const ANCHOR_FRACTION: u8 = 1;
+
+static ANCHOR: AtomicU8 = AtomicU8::new(ANCHOR_CORNER);
+static FRAC_X: AtomicI32 = AtomicI32::new(50);
+static FRAC_Y: AtomicI32 = AtomicI32::new(20);
+static ABSOLUTE: Mutex<Option<(i32, i32)>> = Mutex::new(None);
+
+/// Screen bounds in pixels, taken from the top-left and bottom-right dots.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Screen {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl Screen {
+    pub fn current() -> Self {
+        unsafe {
+            Self {
+                left: dotx(DOT_TL),
+                top: doty(DOT_TL),
+                right: dotx(DOT_BR),
+                bottom: doty(DOT_BR),
+            }
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.right - self.left
+    }
+
+    pub fn height(&self) -> i32 {
+        self.bottom - self.top
+    }
+
+    /// True when the dots coincide or cross, as while the window is
+    /// minimized. Nothing sized from the screen can be drawn then.
+    pub fn is_empty(&self) -> bool {
+        self.width() <= 0 || self.height() <= 0
+    }
+}
+
+/// Tracks whether the screen was empty last frame, so an empty screen is
+/// logged once rather than every frame.
+#[derive(Debug, Default)]
+struct ScreenCheck {
+    empty: bool,
+}
+
+impl ScreenCheck {
+    const fn new() -> Self {
+        Self { empty: false }
+    }
+
+    /// Whether `screen` can be drawn on, logging when it first becomes empty.
+    fn check(&mut self, screen: Screen) -> bool {
+        let empty = screen.is_empty();
+        if empty && !self.empty {
+            log(&format!(
+                "Screen has no area ({}x{}), skipping drawing until it does",
+                screen.width(),
+                screen.height()
+            ));
+        }
+        self.empty = empty;
+        !empty
+    }
+}
+
+static SCREEN_CHECK: Mutex<ScreenCheck> = Mutex::new(ScreenCheck::new());
+
+/// Whether the current screen has any area to draw into this frame.
+pub fn screen_usable() -> bool {
+    SCREEN_CHECK.lock().unwrap_or_else(|e| e.into_inner()).check(Screen::current())
+}
+
+/// A rectangle in screen pixels.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+}
+
+impl Rect {
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.x + self.w && y >= self.y && y < self.y + self.h
+    }
+}
+
+/// Share of `screen`, in percent, covered by the part of `panel` on it, or
+/// `None` if the screen has no area.
+pub fn coverage_percent(panel: Rect, screen: Screen) -> Option<f64> {
+    if screen.is_empty() {
+        return None;
+    }
+    let w = (panel.x + panel.w).min(screen.right) - panel.x.max(screen.left);
+    let h = (panel.y + panel.h).min(screen.bottom) - panel.y.max(screen.top);
+    let covered = w.max(0) as f64 * h.max(0) as f64;
+    Some(covered * 100.0 / (screen.width() as f64 * screen.height() as f64))
+}
+
+static PANEL_RECT: Mutex<Option<Rect>> = Mutex::new(None);
+
+/// Remembers where the panel was last drawn, for mouse hit testing.
+pub fn set_panel_rect(rect: Option<Rect>) {
+    *PANEL_RECT.lock().unwrap_or_else(|e| e.into_inner()) = rect;
+}
+
+/// Where the panel was last drawn, if it is visible.
+pub fn panel_rect() -> Option<Rect> {
+    *PANEL_RECT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Back to corner placement with the default fractions, and no panel drawn.
+pub fn reset() {
+    set_corner();
+    FRAC_X.store(50, Ordering::Relaxed);
+    FRAC_Y.store(20, Ordering::Relaxed);
+    set_panel_rect(None);
+    set_absolute(None);
+    *SCREEN_CHECK.lock().unwrap_or_else(|e| e.into_inner()) = ScreenCheck::new();
+}
+
+/// Switches to fractional placement. Percentages must be in `0..=100`.
+pub fn set_fraction(xpct: i32, ypct: i32) {
+    FRAC_X.store(xpct, Ordering::Relaxed);
+    FRAC_Y.store(ypct, Ordering::Relaxed);
+    ANCHOR.store(ANCHOR_FRACTION, Ordering::Relaxed);
+}
+
+/// The placement percentages while placing by fraction, `None` in corner
+/// mode.
+pub fn fraction() -> Option<(i32, i32)> {
+    (ANCHOR.load(Ordering::Relaxed) == ANCHOR_FRACTION)
+        .then(|| (FRAC_X.load(Ordering::Relaxed), FRAC_Y.load(Ordering::Relaxed)))
+}
+
+/// Returns to placement by pixel offset from the top-left corner.
+pub fn set_corner() {
+    ANCHOR.store(ANCHOR_CORNER, Ordering::Relaxed);
+}
+
+/// The absolute panel position in screen pixels, if one is set.
+pub fn absolute() -> Option<(i32, i32)> {
+    *ABSOLUTE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Sets or clears (`None`) the absolute position. While set, it is used
+/// instead of the anchor mode, which is kept for when it is cleared.
+pub fn set_absolute(pos: Option<(i32, i32)>) {
+    *ABSOLUTE.lock().unwrap_or_else(|e| e.into_inner()) = pos;
+}
+
+/// Keeps a `w` x `h` panel at `(x, y)` fully on screen where possible.
+/// Panels larger than the screen are pinned to the top-left edge.
+pub fn clamp_to_screen(screen: Screen, x: i32, y: i32, w: c_int, h: c_int) -> (i32, i32) {
+    let x = x.min(screen.right - w).max(screen.left);
+    let y = y.min(screen.bottom - h).max(screen.top);
+    (x, y)
+}
+
+/// Places the centre of a `w` x `h` panel at `xpct`/`ypct` percent of the
+/// screen, then clamps it on screen.
+pub fn fraction_position(screen: Screen, xpct: i32, ypct: i32, w: c_int, h: c_int) -> (i32, i32) {
+    let cx = screen.left + screen.width() * xpct / 100;
+    let cy = screen.top + screen.height() * ypct / 100;
+    clamp_to_screen(screen, cx - w / 2, cy - h / 2, w, h)
+}
+
+/// Top-left corner for a `w` x `h` panel: the absolute position if set,
+/// otherwise from the current anchor mode.
+pub fn panel_origin(w: c_int, h: c_int) -> (i32, i32) {
+    let screen = Screen::current();
+    if let Some((x, y)) = absolute() {
+        return clamp_to_screen(screen, x, y, w, h);
+    }
+    match ANCHOR.load(Ordering::Relaxed) {
+        ANCHOR_FRACTION => fraction_position(
+            screen,
+            FRAC_X.load(Ordering::Relaxed),
+            FRAC_Y.load(Ordering::Relaxed),
+            w,
+            h,
+        ),
+        _ => (
+            screen.left + OVERLAY_X.load(Ordering::Relaxed),
+            screen.top + OVERLAY_Y.load(Ordering::Relaxed),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Screen = Screen { left: 0, top: 0, right: 800, bottom: 600 };
+
+    #[test]
+    fn fifty_percent_centres_the_panel() {
+        assert_eq!(fraction_position(SCREEN, 50, 50, 180, 80), (310, 260));
+    }
+
+    #[test]
+    fn respects_screen_origin() {
+        let screen = Screen { left: 100, top: 50, right: 900, bottom: 650 };
+        assert_eq!(fraction_position(screen, 50, 20, 180, 80), (410, 130));
+    }
+
+    #[test]
+    fn extreme_percentages_stay_on_screen() {
+        assert_eq!(fraction_position(SCREEN, 0, 0, 180, 80), (0, 0));
+        assert_eq!(fraction_position(SCREEN, 100, 100, 180, 80), (620, 520));
+    }
+
+    #[test]
+    fn rect_contains_is_half_open() {
+        let r = Rect { x: 10, y: 20, w: 5, h: 5 };
+        assert!(r.contains(10, 20));
+        assert!(r.contains(14, 24));
+        assert!(!r.contains(15, 20));
+        assert!(!r.contains(10, 25));
+        assert!(!r.contains(9, 21));
+    }
+
+    #[test]
+    fn coinciding_dots_are_an_empty_screen() {
+        let minimized = Screen { left: 0, top: 0, right: 0, bottom: 0 };
+        let flat = Screen { left: 0, top: 300, right: 800, bottom: 300 };
+        let crossed = Screen { left: 800, top: 0, right: 0, bottom: 600 };
+        assert!(minimized.is_empty() && flat.is_empty() && crossed.is_empty());
+        assert!(!SCREEN.is_empty());
+    }
+
+    #[test]
+    fn empty_screen_is_logged_once_per_occurrence() {
+        let minimized = Screen { left: 100, top: 100, right: 100, bottom: 100 };
+        let mut check = ScreenCheck::new();
+        crate::testing::take_log();
+        assert!(check.check(SCREEN));
+        assert!(!check.check(minimized));
+        assert!(!check.check(minimized));
+        assert_eq!(crate::testing::take_log().len(), 1);
+        assert!(check.check(SCREEN));
+        assert!(!check.check(minimized));
+        assert_eq!(crate::testing::take_log().len(), 1);
+    }
+
+    #[test]
+    fn coverage_is_panel_over_screen_area() {
+        let panel = Rect { x: 10, y: 10, w: 200, h: 120 };
+        assert_eq!(coverage_percent(panel, SCREEN), Some(5.0));
+        let whole = Rect { x: 0, y: 0, w: 800, h: 600 };
+        assert_eq!(coverage_percent(whole, SCREEN), Some(100.0));
+    }
+
+    #[test]
+    fn coverage_counts_only_the_part_on_screen() {
+        let half_off = Rect { x: 700, y: 0, w: 200, h: 600 };
+        assert_eq!(coverage_percent(half_off, SCREEN), Some(12.5));
+        let off = Rect { x: 900, y: 0, w: 10, h: 10 };
+        assert_eq!(coverage_percent(off, SCREEN), Some(0.0));
+    }
+
+    #[test]
+    fn coverage_needs_a_screen_with_area() {
+        let minimized = Screen { left: 0, top: 0, right: 0, bottom: 0 };
+        assert_eq!(coverage_percent(Rect { x: 0, y: 0, w: 10, h: 10 }, minimized), None);
+    }
+
+    #[test]
+    fn oversized_panel_pins_to_top_left() {
+        assert_eq!(clamp_to_screen(SCREEN, 50, 50, 1000, 700), (0, 0));
+    }
+}
@@ -0,0 +1,146 @@
+//! Experience needed for the next level.
+//!
+//! The client only exposes `exp2level`, so the threshold is found by binary
+//! search over experience values. It is constant within a level, so the last
+//! result is cached and only recomputed when the level changes.
+
+use std::ffi::c_int;
+use std::sync::Mutex;
+
+use crate::game;
+
+/// Where the next level starts.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Next {
+    /// At this much experience.
+    At(c_int),
+    /// Nowhere: the level is the maximum.
+    MaxLevel,
+    /// Not known, because `level_of` gave a bogus answer.
+    Unknown,
+}
+
+/// Smallest experience at which `level_of` reports more than `level`. A
+/// bogus answer from `level_of` (`None`) anywhere in the search gives
+/// [`Next::Unknown`], never [`Next::MaxLevel`].
+///
+/// `level_of` must never decrease as experience grows.
+pub fn next_threshold(level: c_int, level_of: impl Fn(c_int) -> Option<c_int>) -> Next {
+    match level_of(c_int::MAX) {
+        None => return Next::Unknown,
+        Some(top) if top <= level => return Next::MaxLevel,
+        Some(_) => {}
+    }
+    let (mut lo, mut hi) = (0, c_int::MAX);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        match level_of(mid) {
+            None => return Next::Unknown,
+            Some(l) if l > level => hi = mid,
+            Some(_) => lo = mid + 1,
+        }
+    }
+    Next::At(lo)
+}
+
+/// The last computed threshold and the level it belongs to. An unknown
+/// threshold is not kept, so the next call searches again.
+#[derive(Debug, Default)]
+pub struct ThresholdCache {
+    entry: Option<(c_int, Next)>,
+}
+
+impl ThresholdCache {
+    pub const fn new() -> Self {
+        Self { entry: None }
+    }
+
+    /// Threshold above `level`, searching only if the level has changed.
+    pub fn get(&mut self, level: c_int, level_of: impl Fn(c_int) -> Option<c_int>) -> Next {
+        match self.entry {
+            Some((cached, threshold)) if cached == level => threshold,
+            _ => {
+                let threshold = next_threshold(level, level_of);
+                self.entry = (threshold != Next::Unknown).then_some((level, threshold));
+                threshold
+            }
+        }
+    }
+}
+
+static CACHE: Mutex<ThresholdCache> = Mutex::new(ThresholdCache::new());
+
+pub fn reset() {
+    *CACHE.lock().unwrap_or_else(|e| e.into_inner()) = ThresholdCache::new();
+}
+
+/// Where the player's next level starts.
+pub fn next_level_xp() -> Next {
+    let Some(level) = game::try_level() else {
+        return Next::Unknown;
+    };
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    cache.get(level, game::try_level_for)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    // Level n starts at 100 * n^2 experience, capped at level 50.
+    fn level_of(xp: c_int) -> Option<c_int> {
+        let mut level = 0;
+        while level < 50 && xp >= 100 * (level + 1) * (level + 1) {
+            level += 1;
+        }
+        Some(level)
+    }
+
+    #[test]
+    fn finds_first_xp_of_next_level() {
+        assert_eq!(next_threshold(0, level_of), Next::At(100));
+        assert_eq!(next_threshold(1, level_of), Next::At(400));
+        assert_eq!(next_threshold(49, level_of), Next::At(250_000));
+    }
+
+    #[test]
+    fn max_level_has_no_threshold() {
+        assert_eq!(next_threshold(50, level_of), Next::MaxLevel);
+    }
+
+    #[test]
+    fn bogus_levels_end_the_search() {
+        let garbage_above = |xp: c_int| if xp >= 1_000_000 { None } else { level_of(xp) };
+        assert_eq!(next_threshold(3, garbage_above), Next::Unknown);
+        let garbage_low = |xp: c_int| if xp < 1000 { None } else { level_of(xp) };
+        assert_eq!(next_threshold(0, garbage_low), Next::Unknown);
+    }
+
+    #[test]
+    fn a_bogus_probe_mid_search_is_not_the_max_level() {
+        // The top probe is fine, but the first midpoint is garbage.
+        let garbage_mid = |xp: c_int| if xp == c_int::MAX / 2 { None } else { level_of(xp) };
+        assert_eq!(next_threshold(3, garbage_mid), Next::Unknown);
+
+        let mut cache = ThresholdCache::new();
+        assert_eq!(cache.get(3, garbage_mid), Next::Unknown);
+        assert_eq!(cache.get(3, level_of), Next::At(1600));
+    }
+
+    #[test]
+    fn cache_searches_once_per_level() {
+        let calls = Cell::new(0);
+        let counting = |xp| {
+            calls.set(calls.get() + 1);
+            level_of(xp)
+        };
+        let mut cache = ThresholdCache::new();
+        assert_eq!(cache.get(3, counting), Next::At(1600));
+        let first = calls.get();
+        assert_eq!(cache.get(3, counting), Next::At(1600));
+        assert_eq!(calls.get(), first);
+        assert_eq!(cache.get(4, counting), Next::At(2500));
+        assert!(calls.get() > first);
+    }
+}
@@ -5,11 +5,103 @@
 //!
 //! Commands:
 //!   #hello   - Display a greeting message
+//!   #version - Show the mod version and whether this is a debug build
 //!   #stats   - Show current HP/Mana/Gold
+//!   #missing - Show how much HP/Mana is below maximum
+//!   #idle    - Show how long since HP/Mana/Gold/Exp last changed
+//!   #ratios  - Show stat efficiency ratios such as mana per INT
+//!   #bits <index> - Show a stat slot as a bit pattern
 //!   #overlay - Toggle a simple HUD overlay
+//!   #statusbar <on|off> - Show HP/MP/level/gold in a bar along the top edge
+//!   #grid <on|off> | spacing <px> - Draw alignment gridlines with labelled offsets
+//!   #overlay rainbow <on|off> - Cycle the overlay title through all hues
+//!   #overlay border <on|off> - Show or hide the overlay's border
+//!   #overlay zeropad <width> - Pad overlay numbers with leading zeros (0 = off)
+//!   #overlay maxlines <n> - Cap the overlay at n lines, paging the rest
+//!   #overlay more - Show the next page of overlay lines (or scroll over it)
+//!   #overlay timerarcs <on|off> - Show each timer as a shrinking arc
+//!   #overlay nextxp <on|off> - Show experience needed for the next level
+//!   #overlay combatlayout <vert|horiz> - Stack or pack the STR/AGI/INT/WIS lines
+//!   #overlay lowhp <percent> - Set and preview the low-HP warning threshold
+//!   #mana threshold <n|off> - Color mana by whether it covers n
+//!   #overlay combattheme <on|off> - Fade the overlay to red while in combat
+//!   #overlay verbose <on|off> - Show every overlay line, then restore
+//!   #overlay layer <name> / #overlay top - Choose the overlay's drawing layer
+//!   #timer <seconds> [label] / #timer clear - Countdown timers in the overlay
+//!   #timer list / #timer remove <label> - List or cancel running timers
+//!   #mark <name> / #mark diff [from to] - Measure stat changes between named marks
+//!   #preset save|load|delete <name> / #preset list - Named overlay layouts
+//!   #goldtrack <on|off> - Track gold income and project it per day
+//!   #overlay posf <x%> <y%> - Place the overlay relative to screen size
+//!   #overlay abs <x> <y> | off - Pin the overlay to a fixed pixel position
+//!   #overlay coverage - Show how much of the screen the overlay covers
+//!   #theme dump - Print the active theme's colors in hex
+//!   #palette [next|prev] - Toggle or page a grid of color swatches
+//!   #blinkspeed <slow|normal|fast> - Set how fast indicators blink
+//!   #inputlat <on [key]|off> - Measure keypress-to-frame latency in the mod
+//!   #config check - Validate rustmod.cfg without applying it
+//!   #config effective - Print the settings exactly as they would be saved
+//!   #config dim <on|off> - Darken the screen behind the overlay menu
+//!   #config prefixmatch <on|off> - Run commands from an unambiguous prefix
+//!   #config unknowncmd <silent|report> - Name unknown # commands in chat
+//!   #config cmdlog <on|off> - Append handled commands to commands.log
+//!   #config pageslide <on|off> - Slide between pages instead of swapping
+//!   #config easing <linear|inout|out> - Shape of slides and fades
+//!   #benchmark overlay [n] - Time building the overlay n times
+//!   #frametimes - Show p50/p95/p99 frame times
+//!   #clock - Show tick and frame counts, frames per tick and FPS
+//!   #fpsstats - Show min/avg/max FPS since the game started
+//!   #health - Grade recent frames per tick as OK, degraded or stalling
+//!   #timesource <tick|wall> - Time features by tick count or by real time
+//!   #addr - Print addresses of the imported globals (`debug` feature only)
+//!   #export csv - Write the HP/mana/gold/exp history to a CSV file
+//!   #bugreport - Write version, config, recent log, FPS and state to a file
+//!
+//! Separate commands with `;` to run several from one line.
+
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, Ordering};
+use std::time::Instant;
+
+mod anim;
+mod bugreport;
+mod cmdlog;
+mod colors;
+mod combat;
+mod commands;
+mod config;
+mod export;
+mod format;
+mod fpsstats;
+mod frametime;
+mod game;
+mod gold;
+mod grid;
+mod health;
+mod history;
+mod idle;
+mod latency;
+mod layout;
+mod levels;
+mod lowhp;
+mod mana;
+mod marks;
+mod menu;
+mod overlay;
+mod pager;
+mod palette;
+mod presets;
+mod ratios;
+mod render;
+mod ring;
+mod statusbar;
+#[cfg(test)]
+mod testing;
+mod timers;
+mod timesource;
 
-use std::ffi::{c_char, c_int, CStr};
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use format::{format_bits, format_mmss, format_thousands, set_bits, wrap};
+use render::{DrawList, NullCanvas};
 
 // ============================================================================
 // FFI Declarations - Client-exported functions and data
@@ -26,11 +118,13 @@ const V_MAX: usize = 200;
 
 // Screen anchor points
 const DOT_TL: c_int = 0;
+const DOT_BR: c_int = 1;
 
-// Color macro (RGB 5-5-5)
-const fn irgb(r: u16, g: u16, b: u16) -> u16 {
-    (r << 10) | (g << 5) | b
-}
+// Mouse events passed to amod_mouse_click
+const MS_LB_DOWN: c_int = 1;
+const MS_RB_DOWN: c_int = 3;
+const MS_WHEEL_UP: c_int = 5;
+const MS_WHEEL_DOWN: c_int = 6;
 
 extern "C" {
     // Logging
@@ -70,6 +164,14 @@ extern "C" {
 
 static SHOW_OVERLAY: AtomicBool = AtomicBool::new(false);
 static FRAME_COUNT: AtomicU32 = AtomicU32::new(0);
+static TICK_COUNT: AtomicU32 = AtomicU32::new(0);
+
+// amod_tick is called at a fixed rate
+const TICKS_PER_SECOND: u32 = 24;
+
+// Overlay offset from the top-left screen corner, in pixels
+static OVERLAY_X: AtomicI32 = AtomicI32::new(10);
+static OVERLAY_Y: AtomicI32 = AtomicI32::new(10);
 
 // ============================================================================
 // Helper Functions
@@ -81,41 +183,122 @@ macro_rules! cstr {
     };
 }
 
-fn get_username() -> String {
+/// Converts to a C string, dropping any interior NUL bytes.
+fn c_text(text: &str) -> CString {
+    CString::new(text.replace('\0', "")).unwrap_or_default()
+}
+
+/// Writes a line of arbitrary text to chat. The text is passed as a `%s`
+/// argument so that `%` characters in it are printed literally.
+#[cfg(not(test))]
+fn chat(text: &str) {
+    let text = c_text(text);
+    unsafe {
+        addline(cstr!("%s"), text.as_ptr());
+    }
+}
+
+/// Writes a line of arbitrary text to the client log.
+#[cfg(not(test))]
+fn log(text: &str) {
+    bugreport::remember(text);
+    let text = c_text(text);
     unsafe {
-        let cstr = CStr::from_ptr(username.as_ptr());
-        cstr.to_string_lossy().into_owned()
+        note(cstr!("%s"), text.as_ptr());
     }
 }
 
+#[cfg(test)]
+use testing::{chat, log};
+
 // ============================================================================
 // Mod Callbacks
 // ============================================================================
 
 #[no_mangle]
 pub extern "C" fn amod_version() -> *const c_char {
-    cstr!("Rust Demo Mod 1.0.0")
+    cstr!(concat!("Rust Demo Mod ", env!("CARGO_PKG_VERSION")))
+}
+
+/// Puts all mod-local state back to its defaults.
+///
+/// A client that reloads the mod without unloading the library keeps every
+/// static from the previous run, so `amod_init` starts from here.
+/// Everything below resets; only `rustmod.cfg` carries over, because it is
+/// read again straight afterwards. Engine colors stay cached since the
+/// client never changes them.
+fn reset_state() {
+    SHOW_OVERLAY.store(false, Ordering::Relaxed);
+    FRAME_COUNT.store(0, Ordering::Relaxed);
+    TICK_COUNT.store(0, Ordering::Relaxed);
+    OVERLAY_X.store(10, Ordering::Relaxed);
+    OVERLAY_Y.store(10, Ordering::Relaxed);
+
+    anim::set_blink_speed(anim::BlinkSpeed::Normal);
+    anim::set_easing(anim::Easing::default());
+    cmdlog::reset();
+    colors::reset();
+    combat::reset();
+    config::reset();
+    fpsstats::with_stats(|s| *s = fpsstats::FpsStats::new());
+    frametime::reset();
+    gold::stop();
+    health::with_window(|w| w.clear());
+    history::with_history(|h| h.clear());
+    idle::with_idle(|i| *i = idle::Idle::new());
+    latency::disable();
+    layout::reset();
+    levels::reset();
+    lowhp::reset();
+    mana::reset();
+    marks::with_marks(|m| m.clear());
+    menu::reset();
+    overlay::reset();
+    palette::reset();
+    presets::with_presets(|p| p.clear());
+    pager::SLIDE.store(true, Ordering::Relaxed);
+    render::reset();
+    statusbar::SHOW.store(false, Ordering::Relaxed);
+    grid::reset();
+    commands::PREFIX_MATCH.store(false, Ordering::Relaxed);
+    commands::set_unknown_cmd(commands::UnknownCmd::default());
+    timers::with_timers(|t| t.clear());
+    timesource::reset();
 }
 
 #[no_mangle]
 pub extern "C" fn amod_init() {
-    unsafe {
-        note(cstr!("Rust Demo Mod initializing..."));
+    log("Rust Demo Mod initializing...");
+
+    reset_state();
+    let issues = config::load().len();
+    if issues > 0 {
+        log(&format!("Rust Demo Mod: {} problem(s) in config, run #config check", issues));
     }
 }
 
 #[no_mangle]
 pub extern "C" fn amod_exit() {
-    unsafe {
-        note(cstr!("Rust Demo Mod shutting down."));
-    }
+    log("Rust Demo Mod shutting down.");
+    log(&format!("Session {}", fpsstats::with_stats(|s| s.summary())));
+
+    // The process is about to end, so pending writes happen now.
+    config::save_if_dirty();
+    cmdlog::flush();
 }
 
 #[no_mangle]
 pub extern "C" fn amod_gamestart() {
-    let name = get_username();
+    combat::reset();
+    gold::stop();
+    history::with_history(|h| h.clear());
+    idle::with_idle(|i| *i = idle::Idle::new());
+    marks::with_marks(|m| m.clear());
+    fpsstats::with_stats(|s| s.start());
+
+    let name = c_text(&game::username());
     unsafe {
-        note(cstr!("Rust Demo Mod: Game started! Welcome, %s"), name.as_ptr() as *const c_char);
+        note(cstr!("Rust Demo Mod: Game started! Welcome, %s"), name.as_ptr());
         addline(cstr!("Rust Demo Mod loaded. Type #hello for commands."));
     }
 }
@@ -123,69 +306,67 @@ pub extern "C" fn amod_gamestart() {
 #[no_mangle]
 pub extern "C" fn amod_tick() {
     // Called 24 times per second
+    let tick = TICK_COUNT.fetch_add(1, Ordering::Relaxed).wrapping_add(1);
+    combat::sample(game::hp());
+    if tick % TICKS_PER_SECOND == 0 {
+        let frames = FRAME_COUNT.load(Ordering::Relaxed);
+        fpsstats::with_stats(|s| s.sample(frames));
+        health::with_window(|w| w.record(tick, frames));
+    }
+
+    let now = timesource::now();
+    let sample = current_sample(now);
+    history::record(sample);
+    idle::with_idle(|i| i.observe(&sample));
+    for label in timers::with_timers(|t| t.tick(now)) {
+        chat(&format!("Timer finished: {}", label));
+    }
 }
 
 #[no_mangle]
 pub extern "C" fn amod_frame() {
     FRAME_COUNT.fetch_add(1, Ordering::Relaxed);
+    frametime::frame();
 
-    if !SHOW_OVERLAY.load(Ordering::Relaxed) {
+    overlay::advance_theme_fade();
+    // Everything drawn is placed relative to the screen, so a minimized
+    // window draws nothing until it has a size again.
+    if !layout::screen_usable() {
+        layout::set_panel_rect(None);
+        latency::frame_done();
         return;
     }
 
-    unsafe {
-        let x = dotx(DOT_TL) + 10;
-        let y = doty(DOT_TL) + 10;
-        let w = 180;
-        let h = 80;
-
-        // Panel background
-        render_rect(x, y, x + w, y + h, irgb(4, 4, 6));
-
-        // Panel border
-        let border_color = irgb(12, 12, 16);
-        render_line(x, y, x + w, y, border_color);
-        render_line(x, y + h, x + w, y + h, border_color);
-        render_line(x, y, x, y + h, border_color);
-        render_line(x + w, y, x + w, y + h, border_color);
-
-        // Title
-        render_text(x + 4, y + 4, whitecolor, 0, cstr!("Rust Demo Mod"));
-
-        // Stats
-        let mut text_y = y + 20;
-
-        // HP
-        let hp_text = format!("HP: {} / {}\0", hp, value[0][V_HP]);
-        render_text(x + 4, text_y, healthcolor, 0, hp_text.as_ptr() as *const c_char);
-        text_y += 14;
-
-        // Mana
-        let mana_text = format!("Mana: {} / {}\0", mana, value[0][V_MANA]);
-        render_text(x + 4, text_y, manacolor, 0, mana_text.as_ptr() as *const c_char);
-        text_y += 14;
-
-        // Gold
-        let gold_text = format!("Gold: {}\0", gold);
-        render_text(x + 4, text_y, irgb(31, 31, 0), 0, gold_text.as_ptr() as *const c_char);
-        text_y += 14;
-
-        // Frame counter
-        let frame_text = format!("Frame: {}\0", FRAME_COUNT.load(Ordering::Relaxed));
-        render_text(x + 4, text_y, textcolor, 0, frame_text.as_ptr() as *const c_char);
+    let mut list = DrawList::new();
+    grid::build(&mut list, layout::Screen::current());
+    statusbar::build(&mut list, layout::Screen::current());
+    if SHOW_OVERLAY.load(Ordering::Relaxed) {
+        overlay::build(&mut list);
+    } else {
+        layout::set_panel_rect(None);
+        menu::close();
     }
+    palette::build(&mut list);
+    menu::build(&mut list);
+    render::flush_to_host(&list);
+
+    latency::frame_done();
 }
 
 #[no_mangle]
 pub extern "C" fn amod_mouse_move(_x: c_int, _y: c_int) {}
 
 #[no_mangle]
-pub extern "C" fn amod_mouse_click(_x: c_int, _y: c_int, _what: c_int) -> c_int {
+pub extern "C" fn amod_mouse_click(x: c_int, y: c_int, what: c_int) -> c_int {
+    if menu::click(x, y, what) || overlay::wheel(x, y, what) {
+        return 1;
+    }
     0 // Don't consume
 }
 
 #[no_mangle]
-pub extern "C" fn amod_keydown(_key: c_int) -> c_int {
+pub extern "C" fn amod_keydown(key: c_int) -> c_int {
+    latency::key_down(key);
     0 // Don't consume
 }
 
@@ -194,8 +375,435 @@ pub extern "C" fn amod_keyup(_key: c_int) -> c_int {
     0
 }
 
+/// Sets `flag` from an `on`/`off` argument and reports the result. Returns
+/// false (after printing usage for `command`) if the argument is not valid.
+fn switch(flag: &AtomicBool, state: &str, label: &str, command: &str) -> bool {
+    match config::parse_bool(state) {
+        Some(on) => {
+            flag.store(on, Ordering::Relaxed);
+            chat(&format!("{}: {}", label, if on { "ON" } else { "OFF" }));
+            true
+        }
+        None => {
+            chat(&format!("Usage: {} <on|off>", command));
+            false
+        }
+    }
+}
+
+fn overlay_posf(args: &[&str]) {
+    if let ["off"] = args {
+        layout::set_corner();
+        config::mark_dirty();
+        chat("Overlay position: corner offset");
+        return;
+    }
+
+    let pct = |s: &str| s.trim_end_matches('%').parse::<i32>().ok().filter(|p| (0..=100).contains(p));
+    match args {
+        [x, y] => match (pct(x), pct(y)) {
+            (Some(x), Some(y)) => {
+                layout::set_fraction(x, y);
+                config::mark_dirty();
+                chat(&format!("Overlay position: {}%, {}% of screen", x, y));
+            }
+            _ => chat("Percentages must be between 0 and 100"),
+        },
+        _ => chat("Usage: #overlay posf <x%> <y%> | off"),
+    }
+}
+
+fn overlay_coverage() {
+    let Some(panel) = layout::panel_rect() else {
+        chat("Overlay is not shown (#overlay to show it)");
+        return;
+    };
+    let screen = layout::Screen::current();
+    match layout::coverage_percent(panel, screen) {
+        Some(pct) => chat(&format!(
+            "Overlay: {}x{} px, {:.1}% of the {}x{} screen",
+            panel.w,
+            panel.h,
+            pct,
+            screen.width(),
+            screen.height()
+        )),
+        None => chat("Screen has no area (minimized?), coverage unknown"),
+    }
+}
+
+fn overlay_abs(args: &[&str]) {
+    let px = |s: &str| s.parse::<i32>().ok().filter(|p| (0..=config::MAX_OFFSET).contains(p));
+    match args {
+        ["off"] => {
+            layout::set_absolute(None);
+            config::mark_dirty();
+            chat("Overlay position: back to anchor placement");
+        }
+        [x, y] => match (px(x), px(y)) {
+            (Some(x), Some(y)) => {
+                layout::set_absolute(Some((x, y)));
+                config::mark_dirty();
+                chat(&format!("Overlay position: fixed at {}, {} (kept on screen)", x, y));
+            }
+            _ => chat(&format!("Coordinates must be between 0 and {}", config::MAX_OFFSET)),
+        },
+        _ => chat("Usage: #overlay abs <x> <y> | off"),
+    }
+}
+
+// Upper bound for #benchmark iterations so a typo can't freeze the client
+const BENCHMARK_MAX: u32 = 100_000;
+const BENCHMARK_DEFAULT: u32 = 1_000;
+
+fn benchmark_overlay(args: &[&str]) {
+    let n = match args {
+        [] => BENCHMARK_DEFAULT,
+        [n] => match n.parse::<u32>() {
+            Ok(n) if n > 0 => n.min(BENCHMARK_MAX),
+            _ => {
+                chat("Usage: #benchmark overlay [n]");
+                return;
+            }
+        },
+        _ => {
+            chat("Usage: #benchmark overlay [n]");
+            return;
+        }
+    };
+
+    let start = Instant::now();
+    for _ in 0..n {
+        let mut list = DrawList::new();
+        overlay::build(&mut list);
+        list.flush(&mut NullCanvas);
+    }
+    let avg_us = start.elapsed().as_secs_f64() * 1_000_000.0 / n as f64;
+    chat(&format!("Overlay build+flush: {:.2} us avg over {} runs (drawing discarded)", avg_us, n));
+}
+
+/// Prints where the client's imported globals live, for matching them up
+/// in external memory tools.
+#[cfg(feature = "debug")]
+fn addresses() {
+    // Taking the address of an extern static needs `unsafe` before Rust 1.82.
+    #[allow(unused_unsafe)]
+    let globals: [(&str, usize); 4] = unsafe {
+        [
+            ("hp", std::ptr::addr_of!(hp) as usize),
+            ("mana", std::ptr::addr_of!(mana) as usize),
+            ("value", std::ptr::addr_of!(value) as usize),
+            ("username", std::ptr::addr_of!(username) as usize),
+        ]
+    };
+    for (name, addr) in globals {
+        chat(&format!("{:<8} 0x{:0width$X}", name, addr, width = 2 * std::mem::size_of::<usize>()));
+    }
+}
+
+/// The `#version` report. `debug` is whether this is a build without
+/// optimizations, which is much slower to draw the overlay every frame.
+fn version_lines(debug: bool) -> Vec<String> {
+    let profile = if debug { "debug" } else { "release" };
+    let mut lines = vec![format!("Rust Demo Mod {} ({} build)", env!("CARGO_PKG_VERSION"), profile)];
+    if debug {
+        lines.push("Debug build - expect slower performance; use a release build for gameplay".to_string());
+    }
+    lines
+}
+
+/// The `#clock` report. Frames per tick and FPS are averages since the mod
+/// loaded, and need at least one tick to mean anything.
+fn clock_lines(ticks: u32, frames: u32) -> Vec<String> {
+    let mut lines = vec![format!("Ticks: {}  Frames: {}", ticks, frames)];
+    if ticks == 0 {
+        lines.push("No ticks yet; the clock starts with the game".to_string());
+    } else {
+        let per_tick = frames as f64 / ticks as f64;
+        lines.push(format!(
+            "Frames per tick: {:.2}  FPS: {:.1} ({} ticks/s)",
+            per_tick,
+            per_tick * TICKS_PER_SECOND as f64,
+            TICKS_PER_SECOND
+        ));
+    }
+    lines
+}
+
+fn export_csv() {
+    match export::write_history() {
+        Ok((path, csv)) => {
+            chat(&format!("Exported {} samples to {}", csv.rows, path.display()));
+            if csv.dropped > 0 {
+                chat(&format!("{} oldest samples left out (file size limit)", csv.dropped));
+            }
+        }
+        Err(e) => chat(&format!("Export failed: {}", e)),
+    }
+}
+
+/// Mod and session state for `#bugreport`.
+fn state_lines() -> Vec<String> {
+    let screen = layout::Screen::current();
+    let level = game::try_level().map_or("bogus".to_string(), |l| l.to_string());
+    let on = |flag: &AtomicBool| config::on_off(flag.load(Ordering::Relaxed));
+    let mut lines = vec![
+        format!("HP: {} / {}  Mana: {} / {}", game::hp(), game::max_hp(), game::mana(), game::max_mana()),
+        format!("Gold: {}  Experience: {}  Level: {}", game::gold(), game::experience(), level),
+        format!("Screen: {},{} to {},{}", screen.left, screen.top, screen.right, screen.bottom),
+        format!("Overlay: {}  Status bar: {}  Grid: {}", on(&SHOW_OVERLAY), on(&statusbar::SHOW), on(&grid::SHOW)),
+        format!("Time source: {}", timesource::source().name()),
+        format!("Timers: {}", timers::with_timers(|t| t.timers().len())),
+        format!("History samples: {}", history::with_history(|h| h.samples().count())),
+    ];
+    lines.extend(clock_lines(TICK_COUNT.load(Ordering::Relaxed), FRAME_COUNT.load(Ordering::Relaxed)));
+    lines
+}
+
+fn bug_report() {
+    let mut version = version_lines(cfg!(debug_assertions));
+    version.push(format!("Platform: {} {}", std::env::consts::OS, std::env::consts::ARCH));
+    version.push(format!("Debug feature: {}", config::on_off(cfg!(feature = "debug"))));
+    let storage = if config::is_in_memory() { " (unusable, settings in memory)" } else { "" };
+    version.push(format!("Config: {}{}", config::config_path().display(), storage));
+
+    let (ticks, frames) = (TICK_COUNT.load(Ordering::Relaxed), FRAME_COUNT.load(Ordering::Relaxed));
+    let mut fps = vec![fpsstats::with_stats(|s| s.summary())];
+    fps.extend(health::with_window(|w| w.report(ticks, frames)));
+
+    let text = bugreport::render(&[
+        ("Version", version),
+        ("Effective config", config::serialize().lines().map(String::from).collect()),
+        ("Recent log", bugreport::recent_log()),
+        ("FPS", fps),
+        ("State", state_lines()),
+    ]);
+    match bugreport::write(&text) {
+        Ok(path) => chat(&format!("Bug report written to {}", path.display())),
+        Err(e) => chat(&format!("Bug report failed: {}", e)),
+    }
+}
+
+fn inputlat(args: &[&str]) {
+    match args {
+        ["on"] => {
+            latency::enable(latency::ANY_KEY);
+            chat("Input latency probe: ON for all keys (mod-side only, shown in overlay)");
+        }
+        ["on", key] => match key.parse::<c_int>() {
+            Ok(key) if key >= 0 => {
+                latency::enable(key);
+                chat(&format!("Input latency probe: ON for key {} (mod-side only, shown in overlay)", key));
+            }
+            _ => chat("Usage: #inputlat on [keycode]"),
+        },
+        ["off"] => {
+            latency::disable();
+            chat("Input latency probe: OFF");
+        }
+        _ => chat("Usage: #inputlat <on [keycode]|off>"),
+    }
+}
+
+const TIMER_USAGE: &str = "Usage: #timer <seconds> [label] | #timer list | #timer remove <label> | #timer clear";
+
+fn timer(args: &[&str]) {
+    match args {
+        ["clear"] => {
+            timers::with_timers(|t| t.clear());
+            chat("Timers cleared");
+        }
+        ["list"] => {
+            let now = timesource::now();
+            let lines: Vec<String> = timers::with_timers(|t| {
+                t.timers()
+                    .iter()
+                    .map(|timer| {
+                        if timer.is_finished() {
+                            format!("{}: finished", timer.label)
+                        } else {
+                            format!("{}: {}", timer.label, format_mmss(timer.remaining_secs(now)))
+                        }
+                    })
+                    .collect()
+            });
+            if lines.is_empty() {
+                chat("No timers running");
+            }
+            lines.iter().for_each(|line| chat(line));
+        }
+        ["remove"] => chat("Usage: #timer remove <label>"),
+        ["remove", label @ ..] => {
+            let label = label.join(" ");
+            match timers::with_timers(|t| t.remove(&label)) {
+                0 => chat(&format!("No timer named {} (see #timer list)", label)),
+                1 => chat(&format!("Timer removed: {}", label)),
+                n => chat(&format!("{} timers removed: {}", n, label)),
+            }
+        }
+        ["clear" | "list", ..] => chat(TIMER_USAGE),
+        [secs, label @ ..] => match secs.parse::<u32>() {
+            Ok(secs) if (1..=timers::MAX_SECONDS).contains(&secs) => {
+                let now = timesource::now();
+                let added = timers::with_timers(|t| {
+                    let label = match label {
+                        [] => format!("Timer {}", t.timers().len() + 1),
+                        words => words.join(" "),
+                    };
+                    t.add(&label, secs, now).then_some(label)
+                });
+                match added {
+                    Some(label) => chat(&format!("Timer started: {} ({})", label, format_mmss(secs))),
+                    None => chat(&format!("Too many timers (max {})", timers::MAX_TIMERS)),
+                }
+            }
+            Ok(_) => chat(&format!("Seconds must be between 1 and {}", timers::MAX_SECONDS)),
+            Err(_) => chat(TIMER_USAGE),
+        },
+        [] => chat(TIMER_USAGE),
+    }
+}
+
+/// The player's vitals at `tick`.
+fn current_sample(tick: u32) -> history::Sample {
+    history::Sample { tick, hp: game::hp(), mana: game::mana(), gold: game::gold(), exp: game::experience() }
+}
+
+fn mark(args: &[&str]) {
+    match args {
+        ["clear"] => {
+            marks::with_marks(|m| m.clear());
+            chat("Marks cleared");
+        }
+        ["list"] => marks::with_marks(|m| {
+            if m.iter().next().is_none() {
+                chat("No marks set");
+            }
+            for (name, sample) in m.iter() {
+                chat(&format!("{}: tick {}", name, sample.tick));
+            }
+        }),
+        ["diff"] => mark_diff(marks::DEFAULT_FROM, marks::DEFAULT_TO),
+        ["diff", from, to] => mark_diff(from, to),
+        [name] if !marks::RESERVED.contains(name) => {
+            let sample = current_sample(timesource::now());
+            marks::with_marks(|m| m.set(name, sample));
+            chat(&format!("Mark set: {}", name));
+        }
+        _ => chat("Usage: #mark <name> | #mark diff [from to] | #mark list | #mark clear"),
+    }
+}
+
+fn preset(args: &[&str]) {
+    match args {
+        ["list"] => presets::with_presets(|p| {
+            let names: Vec<&str> = p.iter().map(|(name, _)| name.as_str()).collect();
+            if names.is_empty() {
+                chat("No presets saved");
+            } else {
+                chat(&format!("Presets: {}", names.join(", ")));
+            }
+        }),
+        [action, name] if matches!(*action, "save" | "load" | "delete") && !presets::valid_name(name) => chat(&format!(
+            "Invalid preset name '{}': use up to {} letters, digits, - or _",
+            name,
+            presets::MAX_NAME
+        )),
+        ["save", name] => {
+            let entries = config::snapshot(presets::KEYS);
+            let saved = presets::with_presets(|p| {
+                if p.get(name).is_none() && p.len() >= presets::MAX_PRESETS {
+                    return false;
+                }
+                p.set(name, entries);
+                true
+            });
+            if saved {
+                config::mark_dirty();
+                chat(&format!("Preset saved: {}", name));
+            } else {
+                chat(&format!("Too many presets (max {}); delete one first", presets::MAX_PRESETS));
+            }
+        }
+        ["load", name] => match presets::with_presets(|p| p.get(name).cloned()) {
+            Some(entries) => {
+                config::apply_entries(&entries);
+                config::mark_dirty();
+                chat(&format!("Preset loaded: {}", name));
+            }
+            None => chat(&format!("No preset named {} (see #preset list)", name)),
+        },
+        ["delete", name] => {
+            if presets::with_presets(|p| p.remove(name)) {
+                config::mark_dirty();
+                chat(&format!("Preset deleted: {}", name));
+            } else {
+                chat(&format!("No preset named {} (see #preset list)", name));
+            }
+        }
+        _ => chat("Usage: #preset save|load|delete <name> | #preset list"),
+    }
+}
+
+fn mark_diff(from: &str, to: &str) {
+    let lines = marks::with_marks(|m| match (m.get(from), m.get(to)) {
+        (Some(a), Some(b)) => Ok(marks::diff_lines((from, a), (to, b))),
+        (None, _) => Err(from.to_string()),
+        (_, None) => Err(to.to_string()),
+    });
+    match lines {
+        Ok(lines) => lines.iter().for_each(|line| chat(line)),
+        Err(name) => chat(&format!("No mark named {} (set one with #mark {})", name, name)),
+    }
+}
+
+/// Longest line sent to chat before it is wrapped.
+const CHAT_COLUMNS: usize = 64;
+
+fn config_effective() {
+    let pending = if config::is_dirty() { "unsaved changes, written on exit" } else { "matches the file" };
+    chat(&format!("Effective config ({}):", pending));
+    for line in config::serialize().lines() {
+        for part in wrap(line, CHAT_COLUMNS) {
+            chat(&part);
+        }
+    }
+}
+
+fn config_check() {
+    if config::is_in_memory() {
+        chat("Config: settings directory unusable, changes will not be saved this session");
+    }
+
+    let path = config::config_path();
+    let text = match config::read_config() {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            chat(&format!("Config: {} not found, defaults in use", path.display()));
+            return;
+        }
+        Err(e) => {
+            chat(&format!("Config: cannot read {} ({})", path.display(), e));
+            return;
+        }
+    };
+
+    let diagnostics = config::parse(&text, config::Mode::Validate);
+    if diagnostics.is_empty() {
+        chat(&format!("Config: {} OK", path.display()));
+        return;
+    }
+    chat(&format!("Config: {} problem(s) in {}", diagnostics.len(), path.display()));
+    for d in &diagnostics {
+        chat(&format!("  {}", d));
+    }
+}
+
+/// # Safety
+///
+/// `buf` must be null or point to a NUL-terminated string.
 #[no_mangle]
-pub extern "C" fn amod_client_cmd(buf: *const c_char) -> c_int {
+pub unsafe extern "C" fn amod_client_cmd(buf: *const c_char) -> c_int {
     let cmd = unsafe {
         if buf.is_null() {
             return 0;
@@ -206,36 +814,185 @@ pub extern "C" fn amod_client_cmd(buf: *const c_char) -> c_int {
         }
     };
 
+    if IN_CLIENT_CMD.swap(true, Ordering::Acquire) {
+        return 0;
+    }
+    let handled = run_chain(cmd, run_command);
+    IN_CLIENT_CMD.store(false, Ordering::Release);
+    handled
+}
+
+/// Set while `amod_client_cmd` runs, so a command reaching the client's
+/// command hook again is passed through rather than run inside itself.
+static IN_CLIENT_CMD: AtomicBool = AtomicBool::new(false);
+
+/// Runs each `;`-separated part of `input` through `run`, in order, skipping
+/// empty parts. Every part runs even after one is not handled; the chain
+/// counts as handled if any part was.
+fn run_chain(input: &str, mut run: impl FnMut(&str) -> c_int) -> c_int {
+    input
+        .split(';')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .fold(0, |handled, part| handled | (run(part) != 0) as c_int)
+}
+
+/// Runs a single command line. Returns 1 if it is one of the mod's commands.
+fn run_command(cmd: &str) -> c_int {
+    let mut words = cmd.split_whitespace();
+    let mut name = words.next().unwrap_or("");
+    let args: Vec<&str> = words.collect();
+
+    if commands::PREFIX_MATCH.load(Ordering::Relaxed) {
+        match commands::resolve(name, commands::NAMES) {
+            commands::Resolved::Command(full) => name = full,
+            commands::Resolved::Ambiguous(candidates) => {
+                chat(&format!("{} is ambiguous: {}", name, candidates.join(", ")));
+                return 1;
+            }
+            commands::Resolved::Unknown => {}
+        }
+    }
+
+    let handled = dispatch(name, &args);
+    if handled != 0 {
+        cmdlog::record(TICK_COUNT.load(Ordering::Relaxed), cmd);
+    } else if let Some(notice) = commands::unknown_notice(name, commands::unknown_cmd()) {
+        chat(&notice);
+    }
+    handled
+}
+
+/// Runs command `name`. Returns 1 if it is one of the mod's commands.
+fn dispatch(name: &str, args: &[&str]) -> c_int {
     unsafe {
-        match cmd {
-            "#hello" => {
+        match (name, args) {
+            ("#hello", []) => {
                 addline(cstr!("=== Rust Demo Mod Commands ==="));
                 addline(cstr!("#hello   - Show this help"));
+                addline(cstr!("#version - Mod version and build profile"));
                 addline(cstr!("#stats   - Display current stats"));
+                addline(cstr!("#missing - Show HP and mana below maximum"));
+                addline(cstr!("#idle    - Time since each stat last changed"));
+                addline(cstr!("#ratios  - Stat efficiency ratios"));
+                addline(cstr!("#bits <index> - Show a stat slot in binary"));
                 addline(cstr!("#overlay - Toggle HUD overlay"));
+                addline(cstr!("#statusbar <on|off> - Status bar along the top edge"));
+                addline(cstr!("#grid <on|off> | spacing <px> - Alignment grid"));
+                addline(cstr!("#timer <seconds> [label] | clear - Countdown timers"));
+                addline(cstr!("#timer list | remove <label> - Manage running timers"));
+                addline(cstr!("#mark <name> | diff [from to] | list | clear - Measure intervals"));
+                addline(cstr!("#preset save|load|delete <name> | list - Overlay layout presets"));
+                addline(cstr!("#goldtrack <on|off> - Track gold per minute and per day"));
+                addline(cstr!("#overlay posf <x%%> <y%%> - Position overlay by screen fraction"));
+                addline(cstr!("#overlay abs <x> <y> | off - Fixed pixel position"));
+                addline(cstr!("#overlay coverage - Share of the screen the overlay covers"));
+                addline(cstr!("#overlay rainbow <on|off> - Rainbow title"));
+                addline(cstr!("#overlay border <on|off> - Panel border"));
+                addline(cstr!("#overlay zeropad <width> - Zero-pad overlay numbers"));
+                addline(cstr!("#overlay maxlines <n> | more - Cap and page overlay lines"));
+                addline(cstr!("#overlay timerarcs <on|off> - Timers as shrinking arcs"));
+                addline(cstr!("#overlay nextxp <on|off> - Next level threshold"));
+                addline(cstr!("#overlay combatlayout <vert|horiz> - Combat stat layout"));
+                addline(cstr!("#overlay lowhp <percent> - Low-HP warning threshold"));
+                addline(cstr!("#mana threshold <n|off> - Mana affordability indicator"));
+                addline(cstr!("#overlay combattheme <on|off> - Combat colors"));
+                addline(cstr!("#overlay verbose <on|off> - Show all overlay lines"));
+                addline(cstr!("#overlay top | layer <name> - Overlay drawing layer"));
+                addline(cstr!("#benchmark overlay [n] - Time overlay rendering"));
+                addline(cstr!("#frametimes - Frame time percentiles"));
+                addline(cstr!("#clock - Tick and frame counters"));
+                addline(cstr!("#fpsstats - Session min/avg/max FPS"));
+                addline(cstr!("#health - Frames per tick check"));
+                addline(cstr!("#timesource <tick|wall> - Clock used by timers and rates"));
+                #[cfg(feature = "debug")]
+                addline(cstr!("#addr - Addresses of imported globals"));
+                addline(cstr!("#theme dump - Print theme colors in hex"));
+                addline(cstr!("#palette [next|prev] - Color swatch reference"));
+                addline(cstr!("#blinkspeed <slow|normal|fast> - Indicator blink speed"));
+                addline(cstr!("#inputlat <on [key]|off> - Keypress-to-frame latency"));
+                addline(cstr!("#config check - Validate rustmod.cfg"));
+                addline(cstr!("#config effective - Settings as they would be saved"));
+                addline(cstr!("#config dim <on|off> - Dim screen behind the overlay menu"));
+                addline(cstr!("#config prefixmatch <on|off> - Accept unambiguous command prefixes"));
+                addline(cstr!("#config unknowncmd <silent|report> - Report unknown # commands"));
+                addline(cstr!("#config cmdlog <on|off> - Log handled commands to a file"));
+                addline(cstr!("#config pageslide <on|off> - Animate page changes"));
+                addline(cstr!("#config easing <linear|inout|out> - Animation curve"));
+                addline(cstr!("#export csv - Save vitals history as CSV"));
+                addline(cstr!("#bugreport - Save diagnostics for a bug report"));
+                1
+            }
+            ("#version", []) => {
+                for line in version_lines(cfg!(debug_assertions)) {
+                    chat(&line);
+                }
                 1
             }
-            "#stats" => {
-                let level = exp2level(experience);
+            ("#stats", []) => {
                 addline(cstr!("=== Player Stats (from Rust) ==="));
-
-                let level_text = format!("Level: {}  Experience: {}\0", level, experience);
-                addline(level_text.as_ptr() as *const c_char);
-
-                let hp_text = format!("HP: {}/{}  Mana: {}/{}\0", hp, value[0][V_HP], mana, value[0][V_MANA]);
-                addline(hp_text.as_ptr() as *const c_char);
-
-                let stats_text = format!("STR: {}  AGI: {}  INT: {}  WIS: {}\0",
-                    value[0][V_STR], value[0][V_AGI], value[0][V_INT], value[0][V_WIS]);
-                addline(stats_text.as_ptr() as *const c_char);
-
-                let gold_text = format!("Gold: {}\0", gold);
-                addline(gold_text.as_ptr() as *const c_char);
+                chat(&format!("Level: {}  Experience: {}", game::level(), game::experience()));
+                chat(&format!("HP: {}/{}  Mana: {}/{}",
+                    game::hp(), game::max_hp(), game::mana(), game::max_mana()));
+                chat(&format!("STR: {}  AGI: {}  INT: {}  WIS: {}",
+                    game::stat(V_STR), game::stat(V_AGI), game::stat(V_INT), game::stat(V_WIS)));
+                chat(&format!("Gold: {}", game::gold()));
+                1
+            }
+            ("#missing", []) => {
+                let hp_missing = game::missing(game::hp(), game::max_hp());
+                let mana_missing = game::missing(game::mana(), game::max_mana());
+                chat(&format!("Missing: {} HP, {} Mana",
+                    format_thousands(hp_missing), format_thousands(mana_missing)));
                 1
             }
-            "#overlay" => {
+            ("#idle", []) => {
+                match idle::with_idle(|i| i.lines(timesource::now())) {
+                    Some(lines) => lines.iter().for_each(|line| chat(line)),
+                    None => chat("No game running yet"),
+                }
+                1
+            }
+            ("#ratios", []) => {
+                let stats = ratios::Stats {
+                    max_hp: game::max_hp(),
+                    max_mana: game::max_mana(),
+                    level: game::level(),
+                    strength: game::stat(V_STR),
+                    intelligence: game::stat(V_INT),
+                    wisdom: game::stat(V_WIS),
+                };
+                addline(cstr!("=== Stat Ratios ==="));
+                for line in ratios::lines(&stats) {
+                    chat(&line);
+                }
+                1
+            }
+            ("#bits", [index]) => {
+                match index.parse::<usize>() {
+                    Ok(idx) if idx < V_MAX => {
+                        let n = game::stat(idx);
+                        chat(&format!("value[0][{}] = {} (0x{:08X})", idx, n, n as u32));
+                        chat(&format_bits(n));
+                        let set: Vec<String> = set_bits(n).iter().map(|b| b.to_string()).collect();
+                        if set.is_empty() {
+                            chat("Set bits: none");
+                        } else {
+                            chat(&format!("Set bits: {}", set.join(", ")));
+                        }
+                    }
+                    _ => chat(&format!("Index must be between 0 and {}", V_MAX - 1)),
+                }
+                1
+            }
+            ("#bits", _) => {
+                chat(&format!("Usage: #bits <0-{}>", V_MAX - 1));
+                1
+            }
+            ("#overlay", []) => {
                 let new_state = !SHOW_OVERLAY.load(Ordering::Relaxed);
                 SHOW_OVERLAY.store(new_state, Ordering::Relaxed);
+                config::mark_dirty();
                 if new_state {
                     addline(cstr!("Overlay: ON"));
                 } else {
@@ -243,7 +1000,623 @@ pub extern "C" fn amod_client_cmd(buf: *const c_char) -> c_int {
                 }
                 1
             }
+            ("#timer", rest) => {
+                timer(rest);
+                1
+            }
+            ("#mark", rest) => {
+                mark(rest);
+                1
+            }
+            ("#preset", rest) => {
+                preset(rest);
+                1
+            }
+            ("#goldtrack", ["on"]) => {
+                gold::start(timesource::now(), game::gold());
+                addline(cstr!("Gold tracking: ON (rate shown in overlay after one minute)"));
+                1
+            }
+            ("#goldtrack", ["off"]) => {
+                gold::stop();
+                addline(cstr!("Gold tracking: OFF"));
+                1
+            }
+            ("#goldtrack", _) => {
+                chat("Usage: #goldtrack <on|off>");
+                1
+            }
+            ("#overlay", ["rainbow", state]) => {
+                if switch(&overlay::RAINBOW, state, "Rainbow title", "#overlay rainbow") {
+                    config::mark_dirty();
+                }
+                1
+            }
+            ("#statusbar", [state]) => {
+                if switch(&statusbar::SHOW, state, "Status bar", "#statusbar") {
+                    config::mark_dirty();
+                }
+                1
+            }
+            ("#statusbar", _) => {
+                chat("Usage: #statusbar <on|off>");
+                1
+            }
+            ("#grid", ["spacing", px]) => {
+                match px.parse::<i32>() {
+                    Ok(px) if (grid::MIN_SPACING..=grid::MAX_SPACING).contains(&px) => {
+                        grid::set_spacing(px);
+                        config::mark_dirty();
+                        chat(&format!("Grid spacing: {} px", px));
+                    }
+                    _ => chat(&format!("Usage: #grid spacing <{}-{}>", grid::MIN_SPACING, grid::MAX_SPACING)),
+                }
+                1
+            }
+            ("#grid", [state]) => {
+                if switch(&grid::SHOW, state, "Grid", "#grid") {
+                    config::mark_dirty();
+                }
+                1
+            }
+            ("#grid", _) => {
+                chat(&format!("Usage: #grid <on|off> | #grid spacing <{}-{}>", grid::MIN_SPACING, grid::MAX_SPACING));
+                1
+            }
+            ("#overlay", ["border", state]) => {
+                if switch(&overlay::BORDER, state, "Overlay border", "#overlay border") {
+                    config::mark_dirty();
+                }
+                1
+            }
+            ("#overlay", ["maxlines", n]) => {
+                match n.parse::<usize>() {
+                    Ok(n) if (overlay::MIN_MAX_LINES..=overlay::MAX_MAX_LINES).contains(&n) => {
+                        overlay::set_max_lines(n);
+                        config::mark_dirty();
+                        chat(&format!("Overlay shows up to {} lines", n));
+                    }
+                    _ => chat(&format!(
+                        "Usage: #overlay maxlines <{}-{}>",
+                        overlay::MIN_MAX_LINES,
+                        overlay::MAX_MAX_LINES
+                    )),
+                }
+                1
+            }
+            ("#overlay", ["timerarcs", state]) => {
+                if switch(&overlay::TIMER_ARCS, state, "Timer arcs", "#overlay timerarcs") {
+                    config::mark_dirty();
+                }
+                1
+            }
+            ("#overlay", ["more"]) => {
+                if !overlay::next_page(overlay::line_count()) {
+                    chat("All overlay lines already fit");
+                }
+                1
+            }
+            ("#overlay", ["zeropad", width]) => {
+                match width.parse::<usize>() {
+                    Ok(width) if width <= overlay::MAX_ZERO_PAD => {
+                        overlay::set_zero_pad(width);
+                        config::mark_dirty();
+                        match width {
+                            0 => chat("Zero padding: off"),
+                            w => chat(&format!("Zero padding: {} digits", w)),
+                        }
+                    }
+                    _ => chat(&format!("Usage: #overlay zeropad <0-{}>", overlay::MAX_ZERO_PAD)),
+                }
+                1
+            }
+            ("#overlay", ["nextxp", state]) => {
+                match config::parse_bool(state) {
+                    Some(on) => {
+                        overlay::set_line(overlay::line::NEXT_XP, on);
+                        config::mark_dirty();
+                        chat(&format!("Next level line: {}", if on { "ON" } else { "OFF" }));
+                    }
+                    None => chat("Usage: #overlay nextxp <on|off>"),
+                }
+                1
+            }
+            ("#overlay", ["combatlayout", name]) => {
+                match overlay::StatLayout::parse(name) {
+                    Some(layout) => {
+                        overlay::set_stat_layout(layout);
+                        config::mark_dirty();
+                        chat(&format!("Combat stat layout: {}", layout.name()));
+                    }
+                    None => chat("Usage: #overlay combatlayout <vert|horiz>"),
+                }
+                1
+            }
+            ("#overlay", ["lowhp", pct]) => {
+                match pct.trim_end_matches('%').parse::<i32>() {
+                    Ok(pct) if (0..=100).contains(&pct) => {
+                        lowhp::set_percent(pct);
+                        lowhp::preview(timesource::now());
+                        config::mark_dirty();
+                        chat(&format!("Low HP warning below {}% (previewing)", pct));
+                    }
+                    _ => chat("Usage: #overlay lowhp <0-100>"),
+                }
+                1
+            }
+            ("#mana", ["threshold", "off"]) => {
+                mana::set_threshold(0);
+                config::mark_dirty();
+                chat("Mana threshold: off");
+                1
+            }
+            ("#mana", ["threshold", n]) => {
+                match n.parse::<i32>() {
+                    Ok(n) if (0..=mana::MAX_THRESHOLD).contains(&n) => {
+                        mana::set_threshold(n);
+                        config::mark_dirty();
+                        match n {
+                            0 => chat("Mana threshold: off"),
+                            n => chat(&format!("Mana threshold: {}", n)),
+                        }
+                    }
+                    _ => chat(&format!("Usage: #mana threshold <0-{}|off>", mana::MAX_THRESHOLD)),
+                }
+                1
+            }
+            ("#mana", _) => {
+                chat(&format!("Usage: #mana threshold <0-{}|off>", mana::MAX_THRESHOLD));
+                1
+            }
+            ("#overlay", ["combattheme", state]) => {
+                if switch(&overlay::COMBAT_THEME, state, "Combat theme", "#overlay combattheme") {
+                    config::mark_dirty();
+                }
+                1
+            }
+            ("#overlay", ["verbose", state]) => {
+                match config::parse_bool(state) {
+                    Some(on) => {
+                        overlay::set_verbose(on);
+                        config::mark_dirty();
+                        chat(&format!("Verbose overlay: {}", if on { "ON" } else { "OFF" }));
+                    }
+                    None => chat("Usage: #overlay verbose <on|off>"),
+                }
+                1
+            }
+            ("#overlay", ["top"]) => {
+                overlay::set_panel_layer(render::Layer::Top);
+                config::mark_dirty();
+                addline(cstr!("Overlay layer: top"));
+                1
+            }
+            ("#overlay", ["layer", name]) => {
+                match render::Layer::parse(name) {
+                    Some(layer) => {
+                        overlay::set_panel_layer(layer);
+                        config::mark_dirty();
+                        chat(&format!("Overlay layer: {}", layer.name()));
+                    }
+                    None => chat("Usage: #overlay layer <background|standard|popup|top>"),
+                }
+                1
+            }
+            ("#overlay", ["posf", rest @ ..]) => {
+                overlay_posf(rest);
+                1
+            }
+            ("#overlay", ["coverage"]) => {
+                overlay_coverage();
+                1
+            }
+            ("#overlay", ["abs", rest @ ..]) => {
+                overlay_abs(rest);
+                1
+            }
+            ("#theme", ["dump"]) => {
+                addline(cstr!("=== Active theme ==="));
+                for line in colors::theme().dump() {
+                    chat(&line);
+                }
+                1
+            }
+            ("#theme", _) => {
+                chat("Usage: #theme dump");
+                1
+            }
+            ("#blinkspeed", []) => {
+                chat(&format!("Blink speed: {}", anim::blink_speed().name()));
+                1
+            }
+            ("#blinkspeed", [speed]) => {
+                match anim::BlinkSpeed::parse(speed) {
+                    Some(speed) => {
+                        anim::set_blink_speed(speed);
+                        config::mark_dirty();
+                        chat(&format!("Blink speed: {}", speed.name()));
+                    }
+                    None => chat("Usage: #blinkspeed <slow|normal|fast>"),
+                }
+                1
+            }
+            ("#inputlat", rest) => {
+                inputlat(rest);
+                1
+            }
+            ("#config", ["dim", state]) => {
+                if switch(&menu::DIM, state, "Dim screen behind menu", "#config dim") {
+                    config::mark_dirty();
+                }
+                1
+            }
+            ("#config", ["prefixmatch", state]) => {
+                if switch(&commands::PREFIX_MATCH, state, "Prefix matching", "#config prefixmatch") {
+                    config::mark_dirty();
+                }
+                1
+            }
+            ("#config", ["unknowncmd", name]) => {
+                match commands::UnknownCmd::parse(name) {
+                    Some(mode) => {
+                        commands::set_unknown_cmd(mode);
+                        config::mark_dirty();
+                        chat(&format!("Unknown commands: {}", mode.name()));
+                    }
+                    None => chat("Usage: #config unknowncmd <silent|report>"),
+                }
+                1
+            }
+            ("#config", ["cmdlog", state]) => {
+                if switch(&cmdlog::ENABLED, state, "Command log", "#config cmdlog") {
+                    config::mark_dirty();
+                }
+                1
+            }
+            ("#config", ["pageslide", state]) => {
+                if switch(&pager::SLIDE, state, "Page slide", "#config pageslide") {
+                    config::mark_dirty();
+                }
+                1
+            }
+            ("#config", ["easing", name]) => {
+                match anim::Easing::parse(name) {
+                    Some(e) => {
+                        anim::set_easing(e);
+                        config::mark_dirty();
+                        chat(&format!("Easing: {}", e.name()));
+                    }
+                    None => chat("Usage: #config easing <linear|inout|out>"),
+                }
+                1
+            }
+            ("#config", ["check"]) => {
+                config_check();
+                1
+            }
+            ("#config", ["effective"]) => {
+                config_effective();
+                1
+            }
+            ("#config", _) => {
+                chat("Usage: #config check | #config effective | #config <setting> <value> (see #hello)");
+                1
+            }
+            ("#benchmark", ["overlay", rest @ ..]) => {
+                benchmark_overlay(rest);
+                1
+            }
+            ("#benchmark", _) => {
+                chat("Usage: #benchmark overlay [n]");
+                1
+            }
+            ("#palette", []) => {
+                let on = !palette::SHOW.fetch_xor(true, Ordering::Relaxed);
+                chat(&format!("Palette: {}", if on { "ON" } else { "OFF" }));
+                1
+            }
+            ("#palette", ["next"]) => {
+                palette::SHOW.store(true, Ordering::Relaxed);
+                palette::next_page();
+                1
+            }
+            ("#palette", ["prev"]) => {
+                palette::SHOW.store(true, Ordering::Relaxed);
+                palette::prev_page();
+                1
+            }
+            ("#clock", []) => {
+                let ticks = TICK_COUNT.load(Ordering::Relaxed);
+                for line in clock_lines(ticks, FRAME_COUNT.load(Ordering::Relaxed)) {
+                    chat(&line);
+                }
+                1
+            }
+            ("#fpsstats", []) => {
+                chat(&fpsstats::with_stats(|s| s.summary()));
+                1
+            }
+            ("#health", []) => {
+                let (ticks, frames) = (TICK_COUNT.load(Ordering::Relaxed), FRAME_COUNT.load(Ordering::Relaxed));
+                for line in health::with_window(|w| w.report(ticks, frames)) {
+                    chat(&line);
+                }
+                1
+            }
+            ("#timesource", []) => {
+                chat(&format!("Time source: {}", timesource::source().name()));
+                1
+            }
+            ("#timesource", [name]) => {
+                match timesource::Source::parse(name) {
+                    Some(source) => {
+                        timesource::set_source(source);
+                        chat(&format!("Time source: {}", source.name()));
+                        chat("Running timers, #goldtrack and #mark measured with the old source will jump");
+                    }
+                    None => chat("Usage: #timesource <tick|wall>"),
+                }
+                1
+            }
+            ("#frametimes", []) => {
+                match frametime::percentiles() {
+                    Some(p) => chat(&format!(
+                        "Frame times ({} frames): p50 {:.1}ms  p95 {:.1}ms  p99 {:.1}ms",
+                        p.samples,
+                        p.p50.as_secs_f64() * 1000.0,
+                        p.p95.as_secs_f64() * 1000.0,
+                        p.p99.as_secs_f64() * 1000.0,
+                    )),
+                    None => chat("No frame times recorded yet"),
+                }
+                1
+            }
+            #[cfg(feature = "debug")]
+            ("#addr", []) => {
+                addresses();
+                1
+            }
+            ("#export", ["csv"]) => {
+                export_csv();
+                1
+            }
+            ("#export", _) => {
+                chat("Usage: #export csv");
+                1
+            }
+            ("#bugreport", []) => {
+                bug_report();
+                1
+            }
             _ => 0,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version() -> &'static CStr {
+        unsafe { CStr::from_ptr(amod_version()) }
+    }
+
+    #[test]
+    fn chains_run_every_part_in_order() {
+        let mut seen = Vec::new();
+        let handled = run_chain(" #overlay ;; #bogus x;#clock  ", |part| {
+            seen.push(part.to_string());
+            (part != "#bogus x") as c_int
+        });
+        assert_eq!(handled, 1);
+        assert_eq!(seen, ["#overlay", "#bogus x", "#clock"]);
+    }
+
+    #[test]
+    fn chain_is_unhandled_only_if_no_part_was() {
+        assert_eq!(run_chain("#bogus; say hi", |_| 0), 0);
+        assert_eq!(run_chain(" ; ", |_| 1), 0);
+        assert_eq!(run_chain("#bogus;#clock", |part| (part == "#clock") as c_int), 1);
+    }
+
+    #[test]
+    fn version_is_nul_terminated() {
+        let bytes = version().to_bytes_with_nul();
+        assert_eq!(bytes.last(), Some(&0));
+        assert!(!bytes[..bytes.len() - 1].contains(&0));
+    }
+
+    #[test]
+    fn version_matches_expected_string() {
+        assert_eq!(version().to_str().unwrap(), "Rust Demo Mod 1.0.0");
+    }
+
+    #[test]
+    fn version_tracks_cargo_manifest() {
+        assert_eq!(version().to_str().unwrap(), format!("Rust Demo Mod {}", env!("CARGO_PKG_VERSION")));
+    }
+
+    #[test]
+    fn version_report_names_the_build_profile() {
+        let release = version_lines(false);
+        assert_eq!(release, [format!("Rust Demo Mod {} (release build)", env!("CARGO_PKG_VERSION"))]);
+        let debug = version_lines(true);
+        assert!(debug[0].ends_with("(debug build)"));
+        assert!(debug[1].contains("use a release build"));
+    }
+
+    #[test]
+    fn clock_derives_fps_from_counters() {
+        assert_eq!(
+            clock_lines(240, 600),
+            ["Ticks: 240  Frames: 600", "Frames per tick: 2.50  FPS: 60.0 (24 ticks/s)"]
+        );
+    }
+
+    #[test]
+    fn clock_before_first_tick_has_no_ratio() {
+        assert_eq!(clock_lines(0, 35), ["Ticks: 0  Frames: 35", "No ticks yet; the clock starts with the game"]);
+    }
+
+    /// Held by tests that drive the lifecycle callbacks, which share the
+    /// global settings store and mod state.
+    static LIFECYCLE: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    #[test]
+    fn exit_flushes_pending_settings() {
+        let _guard = LIFECYCLE.lock().unwrap_or_else(|e| e.into_inner());
+        let fs = testing::MemFs::default();
+        config::set_store(config::Store::with_fs("mem", fs.clone()));
+        config::mark_dirty();
+        amod_exit();
+        assert_eq!(fs.file(&config::config_path()), Some(config::serialize()));
+
+        let read_only = testing::MemFs::read_only();
+        config::set_store(config::Store::with_fs("mem", read_only.clone()));
+        testing::take_log();
+        config::mark_dirty();
+        amod_exit();
+        assert_eq!(read_only.file(&config::config_path()), None);
+        let log = testing::take_log();
+        assert_eq!(log.len(), 3, "{:?}", log);
+        assert!(log[1].starts_with("Session FPS"));
+        assert!(log[2].contains("cannot write settings"));
+
+        config::set_store(config::Store::new(config::MOD_DIR));
+    }
+
+    #[test]
+    fn exports_go_through_the_settings_store() {
+        let _guard = LIFECYCLE.lock().unwrap_or_else(|e| e.into_inner());
+        let fs = testing::MemFs::default();
+        config::set_store(config::Store::with_fs("mem", fs.clone()));
+        let (path, csv) = export::write_history().unwrap();
+        assert_eq!(fs.file(&path), Some(csv.text));
+        let path = bugreport::write("report\n").unwrap();
+        assert_eq!(fs.file(&path).as_deref(), Some("report\n"));
+        config::set_store(config::Store::new(config::MOD_DIR));
+    }
+
+    #[test]
+    fn command_log_goes_through_the_settings_store() {
+        let _guard = LIFECYCLE.lock().unwrap_or_else(|e| e.into_inner());
+        let fs = testing::MemFs::default();
+        config::set_store(config::Store::with_fs("mem", fs.clone()));
+        cmdlog::reset();
+        cmdlog::ENABLED.store(true, Ordering::Relaxed);
+        cmdlog::record(7, "#hello");
+        cmdlog::flush();
+        let text = fs.file(&config::mod_dir().join(cmdlog::LOG_FILE)).unwrap();
+        assert!(text.ends_with(" tick 7: #hello\n"), "{:?}", text);
+
+        config::set_store(config::Store::with_fs("mem", testing::MemFs::read_only()));
+        testing::take_log();
+        cmdlog::record(8, "#hello");
+        cmdlog::flush();
+        assert!(!cmdlog::ENABLED.load(Ordering::Relaxed));
+        let log = testing::take_log();
+        assert!(log[0].contains("command logging turned off"), "{:?}", log);
+
+        cmdlog::reset();
+        config::set_store(config::Store::new(config::MOD_DIR));
+    }
+
+    #[test]
+    fn timer_words_that_are_not_seconds_get_the_usage() {
+        testing::take_chat();
+        timer(&["list", "x"]);
+        timer(&["clear", "all"]);
+        timer(&["soon"]);
+        timer(&["0"]);
+        let range = format!("Seconds must be between 1 and {}", timers::MAX_SECONDS);
+        assert_eq!(testing::take_chat(), [TIMER_USAGE, TIMER_USAGE, TIMER_USAGE, range.as_str()]);
+    }
+
+    #[test]
+    fn every_command_name_is_dispatched() {
+        let _guard = LIFECYCLE.lock().unwrap_or_else(|e| e.into_inner());
+        config::set_store(config::Store::with_fs("mem", testing::MemFs::default()));
+        for name in commands::NAMES {
+            assert_eq!(dispatch(name, &[]), 1, "{} is passed through", name);
+        }
+        assert_eq!(dispatch("#nosuchcommand", &[]), 0);
+
+        reset_state();
+        config::set_store(config::Store::new(config::MOD_DIR));
+        testing::take_chat();
+        testing::take_log();
+    }
+
+    #[test]
+    fn config_check_tells_a_missing_file_from_an_unreadable_one() {
+        let _guard = LIFECYCLE.lock().unwrap_or_else(|e| e.into_inner());
+        let path = config::Store::new("mem").path();
+
+        config::set_store(config::Store::with_fs("mem", testing::MemFs::default()));
+        testing::take_chat();
+        config_check();
+        assert_eq!(testing::take_chat(), [format!("Config: {} not found, defaults in use", path.display())]);
+
+        config::set_store(config::Store::with_fs("mem", testing::MemFs::unreadable()));
+        config_check();
+        let chat = testing::take_chat();
+        assert_eq!(chat.len(), 1);
+        assert!(chat[0].starts_with(&format!("Config: cannot read {} (", path.display())), "{:?}", chat);
+
+        config::set_store(config::Store::new(config::MOD_DIR));
+    }
+
+    #[test]
+    fn presets_restore_fractional_placement_and_hidden_lines() {
+        let _guard = LIFECYCLE.lock().unwrap_or_else(|e| e.into_inner());
+        config::set_store(config::Store::with_fs("mem", testing::MemFs::default()));
+
+        overlay_posf(&["30", "70"]);
+        overlay::set_line(overlay::line::FRAME, false);
+        overlay::set_line(overlay::line::NEXT_XP, true);
+        overlay::set_zero_pad(3);
+        preset(&["save", "farm"]);
+
+        overlay_posf(&["off"]);
+        overlay::set_line(overlay::line::FRAME, true);
+        overlay::set_line(overlay::line::NEXT_XP, false);
+        overlay::set_zero_pad(0);
+        preset(&["load", "farm"]);
+
+        assert_eq!(layout::fraction(), Some((30, 70)));
+        let lines = (overlay::line::DEFAULT & !overlay::line::FRAME) | overlay::line::NEXT_XP;
+        assert_eq!(overlay::selected_lines(), lines);
+        assert_eq!(overlay::zero_pad(), 3);
+        assert_eq!(testing::take_chat().last().map(String::as_str), Some("Preset loaded: farm"));
+
+        reset_state();
+        config::set_store(config::Store::new(config::MOD_DIR));
+    }
+
+    #[test]
+    fn reinit_resets_state_but_keeps_saved_settings() {
+        let _guard = LIFECYCLE.lock().unwrap_or_else(|e| e.into_inner());
+        config::set_store(config::Store::with_fs("mem", testing::MemFs::default()));
+
+        // A saved setting, and session state that is never saved.
+        SHOW_OVERLAY.store(true, Ordering::Relaxed);
+        config::mark_dirty();
+        TICK_COUNT.store(500, Ordering::Relaxed);
+        latency::enable(latency::ANY_KEY);
+        timers::with_timers(|t| t.add("buff", 60, 500));
+        history::record(history::Sample { tick: 480, hp: 1, mana: 1, gold: 1, exp: 1 });
+
+        amod_exit();
+        amod_init();
+
+        assert!(SHOW_OVERLAY.load(Ordering::Relaxed));
+        assert_eq!(TICK_COUNT.load(Ordering::Relaxed), 0);
+        assert!(!latency::is_enabled());
+        assert!(timers::with_timers(|t| t.timers().is_empty()));
+        assert_eq!(history::with_history(|h| h.samples().count()), 0);
+
+        reset_state();
+        config::set_store(config::Store::new(config::MOD_DIR));
+        testing::take_log();
+    }
+}
@@ -0,0 +1,94 @@
+//! Low-HP warning (`#overlay lowhp`).
+//!
+//! While HP is below the threshold percentage of maximum HP, the overlay's
+//! HP line blinks in the combat color. Changing the threshold previews the
+//! warning for [`PREVIEW_TICKS`] so the user sees what it looks like.
+
+use std::ffi::c_int;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Mutex;
+
+use crate::TICKS_PER_SECOND;
+
+/// How long the warning is forced on after changing the threshold.
+pub const PREVIEW_TICKS: u32 = 2 * TICKS_PER_SECOND;
+
+/// Threshold used until the user sets one.
+pub const DEFAULT_PERCENT: i32 = 25;
+
+static PERCENT: AtomicI32 = AtomicI32::new(DEFAULT_PERCENT);
+
+/// Tick at which the current preview ends.
+static PREVIEW_UNTIL: Mutex<Option<u32>> = Mutex::new(None);
+
+pub fn percent() -> i32 {
+    PERCENT.load(Ordering::Relaxed)
+}
+
+/// Sets the threshold. `pct` must be in `0..=100`.
+pub fn set_percent(pct: i32) {
+    PERCENT.store(pct, Ordering::Relaxed);
+}
+
+/// Default threshold, no preview running.
+pub fn reset() {
+    set_percent(DEFAULT_PERCENT);
+    *PREVIEW_UNTIL.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Forces the warning on from tick `now` for [`PREVIEW_TICKS`].
+pub fn preview(now: u32) {
+    *PREVIEW_UNTIL.lock().unwrap_or_else(|e| e.into_inner()) = Some(now.wrapping_add(PREVIEW_TICKS));
+}
+
+/// True while HP is below `pct` percent of `max`. A zero threshold never
+/// warns, and an unknown maximum (0 or less) never warns.
+pub fn is_low(hp: c_int, max: c_int, pct: i32) -> bool {
+    max > 0 && (hp as i64) * 100 < (max as i64) * pct as i64
+}
+
+fn previewing(until: Option<u32>, now: u32) -> bool {
+    until.is_some_and(|until| until.wrapping_sub(now) as i32 > 0)
+}
+
+/// Whether the HP line should show its warning appearance at tick `now`.
+pub fn warning(hp: c_int, max: c_int, now: u32) -> bool {
+    let until = *PREVIEW_UNTIL.lock().unwrap_or_else(|e| e.into_inner());
+    previewing(until, now) || is_low(hp, max, percent())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn low_below_threshold_only() {
+        assert!(is_low(24, 100, 25));
+        assert!(!is_low(25, 100, 25));
+        assert!(!is_low(0, 100, 0));
+        assert!(is_low(99, 100, 100));
+        assert!(!is_low(0, 0, 50));
+    }
+
+    #[test]
+    fn threshold_math_does_not_overflow() {
+        assert!(is_low(c_int::MAX / 2, c_int::MAX, 60));
+        assert!(!is_low(c_int::MAX, c_int::MAX, 100));
+    }
+
+    #[test]
+    fn preview_ends_after_its_duration() {
+        let until = Some(100 + PREVIEW_TICKS);
+        assert!(previewing(until, 100));
+        assert!(previewing(until, 100 + PREVIEW_TICKS - 1));
+        assert!(!previewing(until, 100 + PREVIEW_TICKS));
+        assert!(!previewing(None, 100));
+    }
+
+    #[test]
+    fn preview_survives_tick_wraparound() {
+        let until = Some(u32::MAX.wrapping_add(PREVIEW_TICKS));
+        assert!(previewing(until, u32::MAX));
+        assert!(previewing(until, 5));
+    }
+}
@@ -0,0 +1,88 @@
+//! Mana affordability indicator (`#mana threshold`).
+//!
+//! The mod does not know spell costs, so the user gives the mana their key
+//! spell needs. While a threshold is set, the overlay's mana line is green
+//! when it can be afforded and red when not, and a thin bar under the line
+//! shows current mana with a tick at the threshold.
+
+use std::ffi::c_int;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+/// Largest accepted threshold.
+pub const MAX_THRESHOLD: i32 = 999_999;
+
+/// Mana needed, or 0 for no indicator.
+static THRESHOLD: AtomicI32 = AtomicI32::new(0);
+
+/// The threshold, if one is set.
+pub fn threshold() -> Option<i32> {
+    Some(THRESHOLD.load(Ordering::Relaxed)).filter(|&t| t > 0)
+}
+
+/// Raw threshold as persisted, 0 meaning off.
+pub fn raw_threshold() -> i32 {
+    THRESHOLD.load(Ordering::Relaxed)
+}
+
+/// Sets the threshold. `mana` must be in `0..=MAX_THRESHOLD`; 0 turns the
+/// indicator off.
+pub fn set_threshold(mana: i32) {
+    THRESHOLD.store(mana, Ordering::Relaxed);
+}
+
+pub fn reset() {
+    set_threshold(0);
+}
+
+pub fn affordable(mana: c_int, threshold: i32) -> bool {
+    mana >= threshold
+}
+
+/// Pixel geometry of the mana bar.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Bar {
+    /// Width of the filled part.
+    pub fill: i32,
+    /// Offset of the threshold tick; the last pixel if the threshold is
+    /// above maximum mana.
+    pub tick: i32,
+}
+
+/// The bar for `mana` out of `max` across `width` pixels, or `None` while
+/// maximum mana is unknown.
+pub fn bar(mana: c_int, max: c_int, threshold: i32, width: i32) -> Option<Bar> {
+    if max <= 0 || width <= 0 {
+        return None;
+    }
+    let scale = |v: c_int| (v.clamp(0, max) as i64 * width as i64 / max as i64) as i32;
+    Some(Bar { fill: scale(mana), tick: scale(threshold).min(width - 1) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn affordable_at_exactly_the_threshold() {
+        assert!(affordable(50, 50));
+        assert!(!affordable(49, 50));
+    }
+
+    #[test]
+    fn bar_scales_to_width() {
+        assert_eq!(bar(50, 100, 25, 200), Some(Bar { fill: 100, tick: 50 }));
+        assert_eq!(bar(100, 100, 100, 200), Some(Bar { fill: 200, tick: 199 }));
+    }
+
+    #[test]
+    fn bar_clamps_out_of_range_values() {
+        assert_eq!(bar(150, 100, 500, 10), Some(Bar { fill: 10, tick: 9 }));
+        assert_eq!(bar(-5, 100, 10, 10), Some(Bar { fill: 0, tick: 1 }));
+        assert_eq!(bar(c_int::MAX, c_int::MAX, MAX_THRESHOLD, 172).map(|b| b.fill), Some(172));
+    }
+
+    #[test]
+    fn no_bar_without_maximum() {
+        assert_eq!(bar(0, 0, 10, 100), None);
+    }
+}
@@ -0,0 +1,154 @@
+//! Named stat snapshots for measuring intervals (`#mark`).
+//!
+//! `#mark <name>` records the current vitals and tick under a name, and
+//! `#mark diff <from> <to>` reports what changed between two marks, with
+//! gold and experience rates over the elapsed time. Like the vitals
+//! history, marks are cleared when a new game starts.
+
+use std::ffi::c_int;
+use std::sync::Mutex;
+
+use crate::format::{format_compact, format_mmss, format_thousands};
+use crate::history::Sample;
+use crate::TICKS_PER_SECOND;
+
+/// Marks kept; setting another drops the oldest.
+pub const MAX_MARKS: usize = 16;
+
+/// Names `#mark diff` compares when none are given.
+pub const DEFAULT_FROM: &str = "start";
+pub const DEFAULT_TO: &str = "end";
+
+/// Words that are subcommands, so cannot name a mark.
+pub const RESERVED: &[&str] = &["diff", "list", "clear"];
+
+#[derive(Debug, Default)]
+pub struct Marks {
+    marks: Vec<(String, Sample)>,
+}
+
+impl Marks {
+    pub const fn new() -> Self {
+        Self { marks: Vec::new() }
+    }
+
+    /// Stores `sample` under `name`, replacing any mark with that name.
+    pub fn set(&mut self, name: &str, sample: Sample) {
+        self.marks.retain(|(n, _)| n != name);
+        if self.marks.len() == MAX_MARKS {
+            self.marks.remove(0);
+        }
+        self.marks.push((name.to_string(), sample));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Sample> {
+        self.marks.iter().find(|(n, _)| n == name).map(|(_, s)| s)
+    }
+
+    /// Marks in the order they were set.
+    pub fn iter(&self) -> impl Iterator<Item = &(String, Sample)> {
+        self.marks.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.marks.clear();
+    }
+}
+
+static MARKS: Mutex<Marks> = Mutex::new(Marks::new());
+
+pub fn with_marks<R>(f: impl FnOnce(&mut Marks) -> R) -> R {
+    let mut guard = MARKS.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut guard)
+}
+
+/// `delta` with an explicit sign, grouped like other chat numbers.
+fn signed(delta: i64) -> String {
+    let text = match c_int::try_from(delta) {
+        Ok(d) => format_thousands(d),
+        Err(_) => format_compact(delta),
+    };
+    if delta > 0 {
+        format!("+{}", text)
+    } else {
+        text
+    }
+}
+
+/// The `#mark diff` report from mark `from` to mark `to`. If `to` was taken
+/// before `from`, the two are swapped so the interval reads forwards.
+pub fn diff_lines(from: (&str, &Sample), to: (&str, &Sample)) -> Vec<String> {
+    let (from, to) = if (to.1.tick.wrapping_sub(from.1.tick) as i32) < 0 { (to, from) } else { (from, to) };
+    let ((from_name, a), (to_name, b)) = (from, to);
+    let ticks = b.tick.wrapping_sub(a.tick);
+    let minutes = ticks as f64 / (60 * TICKS_PER_SECOND) as f64;
+
+    let delta = |x: c_int, y: c_int| y as i64 - x as i64;
+    let with_rate = |label: &str, d: i64| {
+        if minutes > 0.0 {
+            format!("{}: {} ({:.1}/min)", label, signed(d), d as f64 / minutes)
+        } else {
+            format!("{}: {}", label, signed(d))
+        }
+    };
+    vec![
+        format!("{} -> {}: {} elapsed", from_name, to_name, format_mmss(ticks / TICKS_PER_SECOND)),
+        with_rate("Gold", delta(a.gold, b.gold)),
+        with_rate("Exp", delta(a.exp, b.exp)),
+        format!("HP: {}  Mana: {}", signed(delta(a.hp, b.hp)), signed(delta(a.mana, b.mana))),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(tick: u32, gold: c_int, exp: c_int) -> Sample {
+        Sample { tick, hp: 100, mana: 50, gold, exp }
+    }
+
+    #[test]
+    fn setting_a_name_again_replaces_it() {
+        let mut marks = Marks::new();
+        marks.set("start", sample(0, 10, 0));
+        marks.set("end", sample(5, 20, 0));
+        marks.set("start", sample(9, 30, 0));
+        assert_eq!(marks.get("start").map(|s| s.gold), Some(30));
+        let names: Vec<&str> = marks.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["end", "start"]);
+    }
+
+    #[test]
+    fn oldest_mark_makes_room() {
+        let mut marks = Marks::new();
+        for i in 0..=MAX_MARKS {
+            marks.set(&format!("m{}", i), sample(i as u32, 0, 0));
+        }
+        assert!(marks.get("m0").is_none());
+        assert!(marks.get(&format!("m{}", MAX_MARKS)).is_some());
+    }
+
+    #[test]
+    fn diff_reports_deltas_and_rates() {
+        let minute = 60 * TICKS_PER_SECOND;
+        let a = sample(1000, 5_000, 100);
+        let b = Sample { hp: 80, mana: 60, ..sample(1000 + 2 * minute, 6_500, 40) };
+        assert_eq!(
+            diff_lines(("start", &a), ("end", &b)),
+            [
+                "start -> end: 02:00 elapsed",
+                "Gold: +1,500 (750.0/min)",
+                "Exp: -60 (-30.0/min)",
+                "HP: -20  Mana: +10",
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_reads_forwards_and_skips_rates_without_time() {
+        let a = sample(500, 10, 0);
+        let b = sample(200, 0, 0);
+        assert_eq!(diff_lines(("late", &a), ("early", &b))[0], "early -> late: 00:12 elapsed");
+        assert_eq!(diff_lines(("x", &a), ("y", &a))[1], "Gold: 0");
+    }
+}
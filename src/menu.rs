@@ -0,0 +1,190 @@
+//! Right-click context menu for the overlay panel.
+//!
+//! Right-clicking the panel opens a small menu of overlay toggles at the
+//! cursor. While it is open the menu is modal: it takes every mouse click,
+//! a left click on an item runs it, and any other click closes the menu.
+//!
+//! With `dim_behind_menu` on, the rest of the screen is covered by a dark
+//! rectangle while the menu is open. The client has no alpha blending, so
+//! this hides the game view underneath; it is off by default.
+
+use std::ffi::c_int;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::colors::{self, irgb, Theme};
+use crate::config::{self, on_off};
+use crate::layout::{self, Rect, Screen};
+use crate::render::{DrawList, Layer, TextFlags};
+use crate::{overlay, MS_LB_DOWN, MS_RB_DOWN};
+
+const MENU_W: i32 = 130;
+const ITEM_H: i32 = 14;
+const PADDING: i32 = 4;
+
+const DIM_COLOR: u16 = irgb(2, 2, 3);
+
+/// Cover the screen behind the open menu (`#config dim`).
+pub static DIM: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Item {
+    Border,
+    Rainbow,
+    OnTop,
+    Close,
+}
+
+const ITEMS: [Item; 4] = [Item::Border, Item::Rainbow, Item::OnTop, Item::Close];
+
+impl Item {
+    fn label(self) -> String {
+        match self {
+            Item::Border => format!("Border: {}", on_off(overlay::BORDER.load(Ordering::Relaxed))),
+            Item::Rainbow => format!("Rainbow title: {}", on_off(overlay::RAINBOW.load(Ordering::Relaxed))),
+            Item::OnTop => format!("Always on top: {}", on_off(overlay::panel_layer() == Layer::Top)),
+            Item::Close => "Close".to_string(),
+        }
+    }
+
+    fn activate(self) {
+        match self {
+            Item::Border => {
+                overlay::BORDER.fetch_xor(true, Ordering::Relaxed);
+                config::mark_dirty();
+            }
+            Item::Rainbow => {
+                overlay::RAINBOW.fetch_xor(true, Ordering::Relaxed);
+                config::mark_dirty();
+            }
+            Item::OnTop => {
+                let layer = if overlay::panel_layer() == Layer::Top { Layer::Standard } else { Layer::Top };
+                overlay::set_panel_layer(layer);
+                config::mark_dirty();
+            }
+            Item::Close => {}
+        }
+    }
+}
+
+fn menu_rect(x: i32, y: i32) -> Rect {
+    Rect { x, y, w: MENU_W, h: ITEMS.len() as i32 * ITEM_H + 2 * PADDING }
+}
+
+/// Index of the item under `(px, py)` for a menu drawn at `(x, y)`.
+fn item_at(x: i32, y: i32, px: i32, py: i32) -> Option<usize> {
+    if !menu_rect(x, y).contains(px, py) {
+        return None;
+    }
+    let row = (py - y - PADDING).div_euclid(ITEM_H);
+    (0..ITEMS.len() as i32).contains(&row).then_some(row as usize)
+}
+
+/// Top-left corner of the open menu.
+static OPEN_AT: Mutex<Option<(i32, i32)>> = Mutex::new(None);
+
+fn open_at() -> Option<(i32, i32)> {
+    *OPEN_AT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn set_open_at(pos: Option<(i32, i32)>) {
+    *OPEN_AT.lock().unwrap_or_else(|e| e.into_inner()) = pos;
+}
+
+pub fn close() {
+    set_open_at(None);
+}
+
+/// Closed, with the backdrop back at its default (off).
+pub fn reset() {
+    close();
+    DIM.store(false, Ordering::Relaxed);
+}
+
+/// Handles a mouse click. Returns true if the click was consumed.
+pub fn click(x: c_int, y: c_int, what: c_int) -> bool {
+    if let Some((mx, my)) = open_at() {
+        if what == MS_LB_DOWN {
+            if let Some(idx) = item_at(mx, my, x, y) {
+                ITEMS[idx].activate();
+            }
+            close();
+        } else if what == MS_RB_DOWN {
+            close();
+        }
+        return true;
+    }
+
+    if what == MS_RB_DOWN && layout::panel_rect().is_some_and(|r| r.contains(x, y)) {
+        let rect = menu_rect(0, 0);
+        set_open_at(Some(layout::clamp_to_screen(Screen::current(), x, y, rect.w, rect.h)));
+        return true;
+    }
+    false
+}
+
+/// Records the menu, and the backdrop if enabled, on the modal layer so
+/// they cover the panel on whatever layer it is.
+pub fn build(list: &mut DrawList) {
+    let Some((x, y)) = open_at() else {
+        return;
+    };
+    let backdrop = DIM.load(Ordering::Relaxed).then(Screen::current);
+    record(list, &colors::theme(), x, y, backdrop);
+}
+
+/// Records the menu at `(x, y)`, over a backdrop across `backdrop` if given.
+fn record(list: &mut DrawList, theme: &Theme, x: i32, y: i32, backdrop: Option<Screen>) {
+    list.set_layer(Layer::Modal);
+
+    if let Some(screen) = backdrop {
+        list.rect(screen.left, screen.top, screen.right, screen.bottom, DIM_COLOR);
+    }
+
+    let rect = menu_rect(x, y);
+    list.rect(rect.x, rect.y, rect.x + rect.w, rect.y + rect.h, theme.background);
+    list.frame(rect.x, rect.y, rect.x + rect.w, rect.y + rect.h, theme.border);
+    for (i, item) in ITEMS.iter().enumerate() {
+        list.text(x + PADDING, y + PADDING + i as i32 * ITEM_H, theme.text, TextFlags::NONE, &item.label());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit_test_maps_rows_to_items() {
+        assert_eq!(item_at(100, 100, 105, 100 + PADDING), Some(0));
+        assert_eq!(item_at(100, 100, 105, 100 + PADDING + ITEM_H), Some(1));
+        assert_eq!(item_at(100, 100, 105, 100 + PADDING + 3 * ITEM_H + ITEM_H - 1), Some(3));
+    }
+
+    #[test]
+    fn hit_test_ignores_padding_and_outside() {
+        assert_eq!(item_at(100, 100, 105, 101), None);
+        assert_eq!(item_at(100, 100, 99, 110), None);
+        assert_eq!(item_at(100, 100, 100 + MENU_W, 110), None);
+        let bottom = 100 + menu_rect(100, 100).h - 1;
+        assert_eq!(item_at(100, 100, 105, bottom), None);
+    }
+
+    #[test]
+    fn menu_covers_a_panel_on_the_top_layer() {
+        let mut list = DrawList::new();
+        list.set_layer(Layer::Top);
+        list.rect(100, 100, 280, 200, 0);
+        let screen = Screen { left: 0, top: 0, right: 800, bottom: 600 };
+        let theme = Theme { background: 1, border: 2, title: 3, text: 4, health: 5, mana: 6, gold: 7, combat: 8 };
+        record(&mut list, &theme, 120, 120, Some(screen));
+        list.set_layer(Layer::Top);
+        list.rect(1, 1, 2, 2, 0);
+
+        let mut canvas = crate::testing::Recorder::default();
+        list.flush(&mut canvas);
+        let rect = menu_rect(120, 120);
+        let menu = format!("rect {} {} {} {}", rect.x, rect.y, rect.x + rect.w, rect.y + rect.h);
+        let order: Vec<&str> = canvas.log.iter().map(String::as_str).take(4).collect();
+        assert_eq!(order, ["rect 100 100 280 200", "rect 1 1 2 2", "rect 0 0 800 600", menu.as_str()]);
+    }
+}
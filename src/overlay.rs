@@ -0,0 +1,723 @@
+//! The HUD panel toggled by `#overlay`.
+
+use std::ffi::c_int;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::colors::Theme;
+use crate::format::{format_compact, format_mmss, format_padded, format_thousands};
+use crate::gold::{self, Rate};
+use crate::layout::Rect;
+use crate::levels::Next;
+use crate::pager::{self, Pager, Slide};
+use crate::render::{self, DrawList, Layer, TextFlags};
+use crate::{anim, colors, combat, game, latency, layout, levels, lowhp, mana, timers, timesource};
+use crate::{FRAME_COUNT, MS_WHEEL_DOWN, MS_WHEEL_UP, TICKS_PER_SECOND};
+use crate::{V_AGI, V_INT, V_STR, V_WIS};
+
+const PANEL_W: i32 = 180;
+const LINE_H: i32 = 14;
+const TITLE_H: i32 = 20;
+const PADDING: i32 = 4;
+
+// Space between stats packed onto one line
+const STAT_GAP: &str = " ";
+
+// Ticks for the rainbow title to sweep through every hue once
+const RAINBOW_PERIOD: u32 = 4 * TICKS_PER_SECOND;
+
+/// Outline the panel (`#overlay border`).
+pub static BORDER: AtomicBool = AtomicBool::new(true);
+
+/// Cycle the title through all hues (`#overlay rainbow`).
+pub static RAINBOW: AtomicBool = AtomicBool::new(false);
+
+/// Draw each timer's remaining time as a shrinking arc (`#overlay timerarcs`).
+pub static TIMER_ARCS: AtomicBool = AtomicBool::new(false);
+
+/// Radius of a timer arc.
+const ARC_R: i32 = 5;
+
+/// Largest zero-padding width (`#overlay zeropad`).
+pub const MAX_ZERO_PAD: usize = 10;
+
+/// Width numbers are zero-padded to, 0 for none (`#overlay zeropad`).
+static ZERO_PAD: AtomicU8 = AtomicU8::new(0);
+
+pub fn zero_pad() -> usize {
+    ZERO_PAD.load(Ordering::Relaxed) as usize
+}
+
+/// Sets the padding width. `width` must be at most [`MAX_ZERO_PAD`].
+pub fn set_zero_pad(width: usize) {
+    ZERO_PAD.store(width.min(MAX_ZERO_PAD) as u8, Ordering::Relaxed);
+}
+
+/// Lines shown below the title before the rest go onto further pages.
+pub const DEFAULT_MAX_LINES: usize = 20;
+pub const MIN_MAX_LINES: usize = 2;
+pub const MAX_MAX_LINES: usize = 100;
+
+static MAX_LINES: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_LINES);
+
+/// Page of lines shown while they do not all fit (`#overlay more`).
+static PAGE: AtomicUsize = AtomicUsize::new(0);
+
+/// The page change being animated, if any (`#config pageslide`).
+static SLIDING: Mutex<Option<Slide>> = Mutex::new(None);
+
+fn set_sliding(slide: Option<Slide>) {
+    *SLIDING.lock().unwrap_or_else(|e| e.into_inner()) = slide;
+}
+
+pub fn max_lines() -> usize {
+    MAX_LINES.load(Ordering::Relaxed)
+}
+
+/// Sets the line cap. `n` must be in `MIN_MAX_LINES..=MAX_MAX_LINES`.
+pub fn set_max_lines(n: usize) {
+    MAX_LINES.store(n, Ordering::Relaxed);
+    PAGE.store(0, Ordering::Relaxed);
+    set_sliding(None);
+}
+
+/// Which of `total` lines to show under a cap of `max`, and the page
+/// indicator's text. Content that fits is shown whole, with no indicator;
+/// otherwise one row goes to the indicator and the lines are paged,
+/// starting with the first (most important) ones.
+fn visible_lines(total: usize, max: usize, page: usize) -> (std::ops::Range<usize>, Option<String>) {
+    if total <= max {
+        return (0..total, None);
+    }
+    let pager = Pager::new(total, max - 1, page);
+    let range = pager.range();
+    let position = format!("{}/{}", pager.page() + 1, pager.pages());
+    let indicator = match total - range.end {
+        0 => position,
+        after => format!("+{} more  {}", after, position),
+    };
+    (range, Some(indicator))
+}
+
+/// Turns to the following (`forward`) or preceding page of `total` lines,
+/// wrapping around, and starts a slide if enabled. Returns false if every
+/// line already fits.
+fn turn_page(total: usize, forward: bool) -> bool {
+    let max = max_lines();
+    if total <= max {
+        return false;
+    }
+    let pager = Pager::new(total, max - 1, PAGE.load(Ordering::Relaxed));
+    let to = if forward { pager.next() } else { pager.prev() };
+    if to != pager.page() && pager::SLIDE.load(Ordering::Relaxed) {
+        let start_frame = FRAME_COUNT.load(Ordering::Relaxed);
+        set_sliding(Some(Slide { from: pager.page(), forward, start_frame }));
+    }
+    PAGE.store(to, Ordering::Relaxed);
+    true
+}
+
+/// Shows the next page of lines, wrapping to the first. Returns false if
+/// every line already fits.
+pub fn next_page(total: usize) -> bool {
+    turn_page(total, true)
+}
+
+/// Pages the panel with the mouse wheel while the pointer is over it.
+/// Returns true if the event was used.
+pub fn wheel(x: i32, y: i32, what: c_int) -> bool {
+    let forward = match what {
+        MS_WHEEL_DOWN => true,
+        MS_WHEEL_UP => false,
+        _ => return false,
+    };
+    if !layout::panel_rect().is_some_and(|r| r.contains(x, y)) {
+        return false;
+    }
+    turn_page(line_count(), forward);
+    true
+}
+
+/// Optional overlay lines, as bits of the visibility mask.
+pub mod line {
+    /// STR/AGI/INT/WIS.
+    pub const STATS: u32 = 1 << 0;
+    /// Experience against the next level's threshold (`#overlay nextxp`).
+    pub const NEXT_XP: u32 = 1 << 1;
+    /// Gold rate, while `#goldtrack` is on.
+    pub const GOLD_RATE: u32 = 1 << 2;
+    /// Running countdowns.
+    pub const TIMERS: u32 = 1 << 3;
+    /// Input latency, while `#inputlat` is on.
+    pub const LATENCY: u32 = 1 << 4;
+    /// Frame counter.
+    pub const FRAME: u32 = 1 << 5;
+
+    pub const ALL: u32 = STATS | NEXT_XP | GOLD_RATE | TIMERS | LATENCY | FRAME;
+    pub const DEFAULT: u32 = ALL & !NEXT_XP;
+
+    /// Each line's name in the `overlay_lines` setting.
+    const NAMES: [(&str, u32); 6] = [
+        ("stats", STATS),
+        ("nextxp", NEXT_XP),
+        ("goldrate", GOLD_RATE),
+        ("timers", TIMERS),
+        ("latency", LATENCY),
+        ("frame", FRAME),
+    ];
+
+    /// The lines in `mask` as a comma-separated list, or `none`.
+    pub fn names(mask: u32) -> String {
+        let names: Vec<&str> = NAMES.iter().filter(|(_, bit)| mask & bit != 0).map(|(name, _)| *name).collect();
+        if names.is_empty() {
+            "none".to_string()
+        } else {
+            names.join(",")
+        }
+    }
+
+    /// Parses a list written by [`names`].
+    pub fn parse(value: &str) -> Option<u32> {
+        if value == "none" {
+            return Some(0);
+        }
+        value.split(',').try_fold(0, |mask, name| {
+            let (_, bit) = NAMES.iter().find(|(n, _)| *n == name.trim())?;
+            Some(mask | bit)
+        })
+    }
+}
+
+/// Which optional lines are shown, and the user's own selection while
+/// verbose mode has replaced it with [`line::ALL`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct LineMask {
+    visible: u32,
+    saved: Option<u32>,
+}
+
+impl LineMask {
+    const fn new() -> Self {
+        Self { visible: line::DEFAULT, saved: None }
+    }
+
+    fn shows(&self, bit: u32) -> bool {
+        self.visible & bit != 0
+    }
+
+    /// Shows or hides one line. In verbose mode this changes the saved
+    /// selection, so it takes effect once verbose mode ends.
+    fn set(&mut self, bit: u32, on: bool) {
+        let mask = self.saved.as_mut().unwrap_or(&mut self.visible);
+        if on {
+            *mask |= bit;
+        } else {
+            *mask &= !bit;
+        }
+    }
+
+    /// Verbose on shows every line and saves the selection; off restores
+    /// it exactly.
+    fn set_verbose(&mut self, on: bool) {
+        match (on, self.saved) {
+            (true, None) => self.saved = Some(std::mem::replace(&mut self.visible, line::ALL)),
+            (false, Some(mask)) => *self = Self { visible: mask, saved: None },
+            _ => {}
+        }
+    }
+}
+
+static LINES: Mutex<LineMask> = Mutex::new(LineMask::new());
+
+fn with_lines<R>(f: impl FnOnce(&mut LineMask) -> R) -> R {
+    let mut guard = LINES.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut guard)
+}
+
+pub fn set_line(bit: u32, on: bool) {
+    with_lines(|l| l.set(bit, on));
+}
+
+pub fn set_verbose(on: bool) {
+    with_lines(|l| l.set_verbose(on));
+}
+
+pub fn verbose() -> bool {
+    with_lines(|l| l.saved.is_some())
+}
+
+/// The user's own line selection, whether or not verbose mode is on.
+pub fn selected_lines() -> u32 {
+    with_lines(|l| l.saved.unwrap_or(l.visible))
+}
+
+/// Replaces the user's line selection; in verbose mode it takes effect once
+/// verbose mode ends.
+pub fn set_selected_lines(mask: u32) {
+    with_lines(|l| *l.saved.as_mut().unwrap_or(&mut l.visible) = mask & line::ALL);
+}
+
+/// Shift to the combat theme while in combat (`#overlay combattheme`).
+pub static COMBAT_THEME: AtomicBool = AtomicBool::new(false);
+
+/// Frames taken to fade fully into or out of the combat theme.
+const THEME_FADE_FRAMES: u32 = 12;
+
+/// Progress of the combat theme fade, `0..=THEME_FADE_FRAMES`.
+static THEME_FADE: AtomicU32 = AtomicU32::new(0);
+
+static PANEL_LAYER: AtomicU8 = AtomicU8::new(1);
+
+/// The layer the panel is drawn on (`#overlay layer`, `#overlay top`).
+pub fn panel_layer() -> Layer {
+    Layer::from_u8(PANEL_LAYER.load(Ordering::Relaxed))
+}
+
+pub fn set_panel_layer(layer: Layer) {
+    PANEL_LAYER.store(layer.to_u8(), Ordering::Relaxed);
+}
+
+/// How the combat stats (STR/AGI/INT/WIS) are arranged (`#overlay combatlayout`).
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum StatLayout {
+    /// One stat per line.
+    Vertical,
+    /// As many stats per line as fit in the panel.
+    #[default]
+    Horizontal,
+}
+
+impl StatLayout {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "vert" => Some(Self::Vertical),
+            "horiz" => Some(Self::Horizontal),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Vertical => "vert",
+            Self::Horizontal => "horiz",
+        }
+    }
+
+    fn from_u8(v: u8) -> Self {
+        match v {
+            0 => Self::Vertical,
+            _ => Self::Horizontal,
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::Vertical => 0,
+            Self::Horizontal => 1,
+        }
+    }
+}
+
+static STAT_LAYOUT: AtomicU8 = AtomicU8::new(1);
+
+pub fn stat_layout() -> StatLayout {
+    StatLayout::from_u8(STAT_LAYOUT.load(Ordering::Relaxed))
+}
+
+pub fn set_stat_layout(layout: StatLayout) {
+    STAT_LAYOUT.store(layout.to_u8(), Ordering::Relaxed);
+}
+
+/// Greedily packs `items` into lines no wider than `max_w`, as measured by
+/// `width`. An item wider than `max_w` gets a line of its own.
+fn pack_lines(items: &[String], max_w: i32, width: impl Fn(&str) -> i32) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for item in items {
+        match lines.last_mut() {
+            Some(line) if width(&format!("{}{}{}", line, STAT_GAP, item)) <= max_w => {
+                line.push_str(STAT_GAP);
+                line.push_str(item);
+            }
+            _ => lines.push(item.clone()),
+        }
+    }
+    lines
+}
+
+/// The combat stat lines for `layout`, fitted to `max_w` pixels.
+/// Values are zero-padded to `pad` digits.
+fn stat_lines(layout: StatLayout, stats: &[(&str, i32)], max_w: i32, pad: usize) -> Vec<String> {
+    let stat = |sep: &str, (name, v): &(&str, i32)| format!("{}{}{}", name, sep, format_padded(*v, pad));
+    match layout {
+        StatLayout::Vertical => stats.iter().map(|s| stat(": ", s)).collect(),
+        StatLayout::Horizontal => {
+            let items: Vec<String> = stats.iter().map(|s| stat(" ", s)).collect();
+            pack_lines(&items, max_w, render::text_width)
+        }
+    }
+}
+
+/// Puts every panel option back to its default.
+pub fn reset() {
+    BORDER.store(true, Ordering::Relaxed);
+    RAINBOW.store(false, Ordering::Relaxed);
+    TIMER_ARCS.store(false, Ordering::Relaxed);
+    set_zero_pad(0);
+    set_max_lines(DEFAULT_MAX_LINES);
+    with_lines(|l| *l = LineMask::new());
+    COMBAT_THEME.store(false, Ordering::Relaxed);
+    THEME_FADE.store(0, Ordering::Relaxed);
+    set_panel_layer(Layer::Standard);
+    set_stat_layout(StatLayout::default());
+}
+
+/// One frame of fading towards the combat theme (`toward_combat`) or away.
+fn fade_step(step: u32, toward_combat: bool) -> u32 {
+    if toward_combat {
+        (step + 1).min(THEME_FADE_FRAMES)
+    } else {
+        step.saturating_sub(1)
+    }
+}
+
+/// Advances the combat theme fade by one frame.
+pub fn advance_theme_fade() {
+    let toward = COMBAT_THEME.load(Ordering::Relaxed) && combat::in_combat();
+    let step = fade_step(THEME_FADE.load(Ordering::Relaxed), toward);
+    THEME_FADE.store(step, Ordering::Relaxed);
+}
+
+/// The active theme blended towards its combat variant by the fade progress.
+fn panel_theme() -> Theme {
+    let theme = colors::theme();
+    match THEME_FADE.load(Ordering::Relaxed) {
+        0 => theme,
+        step => theme.lerp(&theme.combat(), anim::eased(step as f32 / THEME_FADE_FRAMES as f32)),
+    }
+}
+
+fn title_color(theme: &Theme, tick: u32) -> u16 {
+    if RAINBOW.load(Ordering::Relaxed) {
+        let hue = (tick % RAINBOW_PERIOD) as f32 * 360.0 / RAINBOW_PERIOD as f32;
+        colors::hsv_to_irgb(hue, 1.0, 1.0)
+    } else {
+        theme.title
+    }
+}
+
+fn hp_color(theme: &Theme, tick: u32) -> u16 {
+    if lowhp::warning(game::hp(), game::max_hp(), tick) && anim::blink_on(tick) {
+        theme.combat
+    } else {
+        theme.health
+    }
+}
+
+fn mana_color(theme: &Theme) -> u16 {
+    match mana::threshold() {
+        Some(t) if mana::affordable(game::mana(), t) => colors::AFFORDABLE,
+        Some(_) => colors::UNAFFORDABLE,
+        None => theme.mana,
+    }
+}
+
+/// Draws the mana bar with its threshold tick along the bottom of the line
+/// starting at `y`, if a threshold is set.
+fn mana_bar(list: &mut DrawList, theme: &Theme, x: i32, y: i32) {
+    let Some(threshold) = mana::threshold() else {
+        return;
+    };
+    let w = PANEL_W - 2 * PADDING;
+    let Some(bar) = mana::bar(game::mana(), game::max_mana(), threshold, w) else {
+        return;
+    };
+    let (top, bottom) = (y + LINE_H - 3, y + LINE_H - 1);
+    list.rect(x, top, x + w, bottom, theme.border);
+    list.rect(x, top, x + bar.fill, bottom, mana_color(theme));
+    list.line(x + bar.tick, top - 1, x + bar.tick, bottom, theme.text);
+}
+
+/// What the panel shows below its title.
+struct Content {
+    /// Text and color, top to bottom.
+    lines: Vec<(String, u16)>,
+    /// Timer arcs, as line index and share of the countdown remaining.
+    arcs: Vec<(usize, f32)>,
+}
+
+/// The stat lines shown below the title, top to bottom.
+/// The mana line is always second, so [`build`] can put its bar under it.
+fn lines(theme: &Theme, tick: u32) -> Content {
+    let mask = with_lines(|l| *l);
+    let shows = |bit| mask.shows(bit);
+    let pad = zero_pad();
+    let num = |n| format_padded(n, pad);
+    let mut lines = vec![
+        (format!("HP: {} / {}", num(game::hp()), num(game::max_hp())), hp_color(theme, tick)),
+        (format!("Mana: {} / {}", num(game::mana()), num(game::max_mana())), mana_color(theme)),
+        (format!("Gold: {}", num(game::gold())), theme.gold),
+    ];
+    let mut arcs = Vec::new();
+
+    let stats = [
+        ("STR", game::stat(V_STR)),
+        ("AGI", game::stat(V_AGI)),
+        ("INT", game::stat(V_INT)),
+        ("WIS", game::stat(V_WIS)),
+    ];
+    if shows(line::STATS) {
+        for text in stat_lines(stat_layout(), &stats, PANEL_W - 2 * PADDING, pad) {
+            lines.push((text, theme.text));
+        }
+    }
+
+    if let Some(secs) = combat::secs_to_full(game::max_hp()) {
+        lines.push((format!("Full HP in {}", format_mmss(secs)), theme.health));
+    }
+
+    // Verbose mode also lists the lines that have nothing to show yet.
+    let verbose = mask.saved.is_some();
+
+    if shows(line::NEXT_XP) {
+        let text = match levels::next_level_xp() {
+            Next::At(threshold) => {
+                format!("Next: {} / {}", format_thousands(game::experience()), format_thousands(threshold))
+            }
+            Next::MaxLevel => "Next: MAX".to_string(),
+            Next::Unknown => "Next: --".to_string(),
+        };
+        lines.push((text, theme.text));
+    }
+
+    match gold::rate(tick, game::gold()) {
+        Some(rate) if shows(line::GOLD_RATE) => {
+            let text = match rate {
+                Rate::WarmingUp => "At this rate: --".to_string(),
+                Rate::Earning { per_minute, per_day } => {
+                    format!("{:.0}/min, {}/day", per_minute, format_compact(per_day))
+                }
+                Rate::Losing { per_minute } => format!("{:.0}/min, losing gold", per_minute),
+            };
+            lines.push((text, theme.gold));
+        }
+        None if verbose => lines.push(("Gold rate: off".to_string(), theme.gold)),
+        _ => {}
+    }
+
+    timers::with_timers(|t| {
+        if !shows(line::TIMERS) {
+            return;
+        }
+        if verbose && t.timers().is_empty() {
+            lines.push(("Timers: none".to_string(), theme.text));
+        }
+        for timer in t.timers() {
+            let text = format!("{}: {}", timer.label, format_mmss(timer.remaining_secs(tick)));
+            let color = match timer.is_finished() {
+                true if anim::blink_on(tick) => theme.combat,
+                true => theme.title,
+                false => theme.text,
+            };
+            if !timer.is_finished() {
+                arcs.push((lines.len(), timer.remaining_frac(tick)));
+            }
+            lines.push((text, color));
+        }
+    });
+
+    if shows(line::LATENCY) {
+        if latency::is_enabled() {
+            let text = match latency::average() {
+                Some(avg) => format!("input->frame: {:.1}ms", avg.as_secs_f64() * 1000.0),
+                None => "input->frame: --".to_string(),
+            };
+            lines.push((text, theme.text));
+        } else if verbose {
+            lines.push(("input->frame: off".to_string(), theme.text));
+        }
+    }
+
+    if shows(line::FRAME) {
+        lines.push((format!("Frame: {}", FRAME_COUNT.load(Ordering::Relaxed)), theme.text));
+    }
+    Content { lines, arcs }
+}
+
+/// Records `lines` one below the other from `(x, y)`.
+fn draw_lines(list: &mut DrawList, lines: &[(String, u16)], x: i32, y: i32) {
+    for (i, (text, color)) in lines.iter().enumerate() {
+        list.text(x, y + i as i32 * LINE_H, *color, TextFlags::NONE, text);
+    }
+}
+
+/// Number of lines the panel has right now, before the cap.
+pub fn line_count() -> usize {
+    lines(&panel_theme(), timesource::now()).lines.len()
+}
+
+/// Records the panel into `list` without drawing anything.
+pub fn build(list: &mut DrawList) {
+    let theme = panel_theme();
+    let tick = timesource::now();
+    let Content { lines, arcs } = lines(&theme, tick);
+    let (shown, indicator) = visible_lines(lines.len(), max_lines(), PAGE.load(Ordering::Relaxed));
+
+    // The lines being slid away from, and the old and new page offsets.
+    let slide = *SLIDING.lock().unwrap_or_else(|e| e.into_inner());
+    let sliding = slide.and_then(|s| {
+        let offsets = s.offsets(FRAME_COUNT.load(Ordering::Relaxed), PANEL_W)?;
+        Some((visible_lines(lines.len(), max_lines(), s.from).0, offsets))
+    });
+    if slide.is_some() && sliding.is_none() {
+        set_sliding(None);
+    }
+    let mut line_rows = shown.len();
+    if let Some((from, _)) = &sliding {
+        line_rows = line_rows.max(from.len());
+    }
+    let rows = line_rows + indicator.is_some() as usize;
+    let w = PANEL_W;
+    let h = TITLE_H + rows as i32 * LINE_H + PADDING;
+    let (x, y) = layout::panel_origin(w, h);
+    layout::set_panel_rect(Some(Rect { x, y, w, h }));
+    list.set_layer(panel_layer());
+
+    // Panel background
+    list.rect(x, y, x + w, y + h, theme.background);
+
+    // Panel border
+    if BORDER.load(Ordering::Relaxed) {
+        list.frame(x, y, x + w, y + h, theme.border);
+    }
+
+    // Title
+    list.text(x + PADDING, y + PADDING, title_color(&theme, tick), TextFlags::NONE, "Rust Demo Mod");
+    if combat::in_combat() && anim::blink_on(tick) {
+        list.text(x + w - PADDING, y + PADDING, theme.combat, TextFlags::RIGHT, "Combat");
+    }
+
+    // Stats
+    let top = y + TITLE_H;
+    match &sliding {
+        Some((from, (old_dx, new_dx))) => {
+            list.set_clip(Some(Rect { x, y: top, w, h: line_rows as i32 * LINE_H }));
+            draw_lines(list, &lines[from.clone()], x + PADDING + old_dx, top);
+            draw_lines(list, &lines[shown.clone()], x + PADDING + new_dx, top);
+            list.set_clip(None);
+        }
+        None => draw_lines(list, &lines[shown.clone()], x + PADDING, top),
+    }
+    if let Some(indicator) = indicator {
+        list.text(x + PADDING, top + line_rows as i32 * LINE_H, theme.text, TextFlags::NONE, &indicator);
+    }
+    if sliding.is_some() {
+        return;
+    }
+    if TIMER_ARCS.load(Ordering::Relaxed) {
+        for (idx, frac) in arcs.into_iter().filter(|(idx, _)| shown.contains(idx)) {
+            let cy = y + TITLE_H + (idx - shown.start) as i32 * LINE_H + LINE_H / 2;
+            list.arc(x + w - PADDING - ARC_R, cy, ARC_R, 0.0, frac, theme.text);
+        }
+    }
+    if shown.contains(&1) {
+        mana_bar(list, &theme, x + PADDING, y + TITLE_H + (1 - shown.start as i32) * LINE_H);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATS: [(&str, i32); 4] = [("STR", 20), ("AGI", 15), ("INT", 30), ("WIS", 25)];
+
+    #[test]
+    fn lines_that_fit_are_all_shown() {
+        assert_eq!(visible_lines(5, 20, 0), (0..5, None));
+        assert_eq!(visible_lines(20, 20, 3), (0..20, None));
+    }
+
+    #[test]
+    fn overflow_keeps_a_row_for_the_indicator() {
+        assert_eq!(visible_lines(25, 20, 0), (0..19, Some("+6 more  1/2".to_string())));
+        assert_eq!(visible_lines(25, 20, 7), (19..25, Some("2/2".to_string())));
+        assert_eq!(visible_lines(3, MIN_MAX_LINES, 0), (0..1, Some("+2 more  1/3".to_string())));
+    }
+
+    #[test]
+    fn indicator_counts_only_later_pages() {
+        assert_eq!(visible_lines(50, 11, 1), (10..20, Some("+30 more  2/5".to_string())));
+        assert_eq!(visible_lines(50, 11, 4), (40..50, Some("5/5".to_string())));
+    }
+
+    #[test]
+    fn vertical_layout_is_one_stat_per_line() {
+        assert_eq!(stat_lines(StatLayout::Vertical, &STATS, PANEL_W, 0), ["STR: 20", "AGI: 15", "INT: 30", "WIS: 25"]);
+    }
+
+    #[test]
+    fn horizontal_layout_fits_panel_on_one_line() {
+        let lines = stat_lines(StatLayout::Horizontal, &STATS, PANEL_W - 2 * PADDING, 0);
+        assert_eq!(lines, ["STR 20 AGI 15 INT 30 WIS 25"]);
+    }
+
+    #[test]
+    fn stat_values_are_zero_padded() {
+        assert_eq!(stat_lines(StatLayout::Vertical, &STATS[..2], PANEL_W, 4), ["STR: 0020", "AGI: 0015"]);
+    }
+
+    #[test]
+    fn horizontal_layout_wraps_when_narrow() {
+        let width = |s: &str| s.len() as i32;
+        let items: Vec<String> = ["STR 20", "AGI 15", "INT 30", "WIS 25"].map(String::from).to_vec();
+        assert_eq!(pack_lines(&items, 13, width), ["STR 20 AGI 15", "INT 30 WIS 25"]);
+        assert_eq!(pack_lines(&items, 12, width), ["STR 20", "AGI 15", "INT 30", "WIS 25"]);
+        assert_eq!(pack_lines(&items, 3, width), ["STR 20", "AGI 15", "INT 30", "WIS 25"]);
+    }
+
+    #[test]
+    fn theme_fade_moves_one_frame_at_a_time_and_clamps() {
+        assert_eq!(fade_step(0, true), 1);
+        assert_eq!(fade_step(THEME_FADE_FRAMES, true), THEME_FADE_FRAMES);
+        assert_eq!(fade_step(5, false), 4);
+        assert_eq!(fade_step(0, false), 0);
+    }
+
+    #[test]
+    fn verbose_shows_everything_and_restores_the_mask() {
+        let mut mask = LineMask::new();
+        mask.set(line::FRAME, false);
+        mask.set_verbose(true);
+        assert_eq!(mask.visible, line::ALL);
+        mask.set_verbose(true);
+        mask.set(line::NEXT_XP, true);
+        assert!(mask.shows(line::NEXT_XP) && mask.shows(line::FRAME));
+        mask.set_verbose(false);
+        assert_eq!(mask, LineMask { visible: (line::DEFAULT & !line::FRAME) | line::NEXT_XP, saved: None });
+    }
+
+    #[test]
+    fn verbose_off_without_on_changes_nothing() {
+        let mut mask = LineMask::new();
+        mask.set_verbose(false);
+        assert_eq!(mask, LineMask::new());
+    }
+
+    #[test]
+    fn line_names_round_trip() {
+        for mask in [0, line::DEFAULT, line::ALL, line::NEXT_XP | line::FRAME] {
+            assert_eq!(line::parse(&line::names(mask)), Some(mask));
+        }
+        assert_eq!(line::names(line::STATS | line::TIMERS), "stats,timers");
+        assert_eq!(line::parse("stats, frame"), Some(line::STATS | line::FRAME));
+        assert_eq!(line::parse("stats,clock"), None);
+    }
+
+    #[test]
+    fn stat_layout_names_round_trip() {
+        for layout in [StatLayout::Vertical, StatLayout::Horizontal] {
+            assert_eq!(StatLayout::parse(layout.name()), Some(layout));
+            assert_eq!(StatLayout::from_u8(layout.to_u8()), layout);
+        }
+        assert_eq!(StatLayout::parse("diagonal"), None);
+    }
+}
@@ -0,0 +1,123 @@
+//! Splitting a list of items into pages, and sliding between them.
+
+use std::sync::atomic::AtomicBool;
+
+use crate::anim;
+
+/// Animate page changes (`#config pageslide`).
+pub static SLIDE: AtomicBool = AtomicBool::new(true);
+
+/// Frames a page slide takes.
+pub const SLIDE_FRAMES: u32 = 8;
+
+/// Position within `items` split into pages of `per_page`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Pager {
+    pub items: usize,
+    pub per_page: usize,
+    page: usize,
+}
+
+impl Pager {
+    /// A pager on `page`, clamped to the last page. `per_page` of 0 is
+    /// treated as 1.
+    pub fn new(items: usize, per_page: usize, page: usize) -> Self {
+        let mut pager = Self { items, per_page: per_page.max(1), page: 0 };
+        pager.page = page.min(pager.pages() - 1);
+        pager
+    }
+
+    /// Number of pages; an empty list still has one (empty) page.
+    pub fn pages(&self) -> usize {
+        ((self.items + self.per_page - 1) / self.per_page).max(1)
+    }
+
+    pub fn page(&self) -> usize {
+        self.page
+    }
+
+    /// Index range of the items on the current page.
+    pub fn range(&self) -> std::ops::Range<usize> {
+        let start = self.page * self.per_page;
+        start..(start + self.per_page).min(self.items)
+    }
+
+    /// The following page, wrapping to the first.
+    pub fn next(&self) -> usize {
+        (self.page + 1) % self.pages()
+    }
+
+    /// The preceding page, wrapping to the last.
+    pub fn prev(&self) -> usize {
+        (self.page + self.pages() - 1) % self.pages()
+    }
+}
+
+/// A slide from page `from` to the current page, started on `start_frame`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Slide {
+    pub from: usize,
+    /// The new page comes in from the right (next) rather than the left.
+    pub forward: bool,
+    pub start_frame: u32,
+}
+
+impl Slide {
+    /// Horizontal offsets of the old and new page at `frame`, for content
+    /// `width` pixels wide. `None` once the slide has finished.
+    pub fn offsets(&self, frame: u32, width: i32) -> Option<(i32, i32)> {
+        let elapsed = frame.wrapping_sub(self.start_frame);
+        if elapsed >= SLIDE_FRAMES {
+            return None;
+        }
+        let moved = (anim::eased(elapsed as f32 / SLIDE_FRAMES as f32) * width as f32).round() as i32;
+        let dir = if self.forward { -1 } else { 1 };
+        Some((dir * moved, dir * (moved - width)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slide_moves_old_page_out_and_new_page_in() {
+        let slide = Slide { from: 0, forward: true, start_frame: 100 };
+        assert_eq!(slide.offsets(100, 200), Some((0, 200)));
+        let (old, new) = slide.offsets(104, 200).unwrap();
+        assert!(old < 0 && new > 0 && old - new == -200);
+        assert_eq!(slide.offsets(100 + SLIDE_FRAMES, 200), None);
+    }
+
+    #[test]
+    fn backward_slide_mirrors() {
+        let slide = Slide { from: 1, forward: false, start_frame: 0 };
+        assert_eq!(slide.offsets(0, 200), Some((0, -200)));
+        let (old, new) = slide.offsets(4, 200).unwrap();
+        assert!(old > 0 && new < 0);
+    }
+
+    #[test]
+    fn splits_into_pages() {
+        let p = Pager::new(25, 10, 2);
+        assert_eq!(p.pages(), 3);
+        assert_eq!(p.range(), 20..25);
+        assert_eq!(Pager::new(25, 10, 0).range(), 0..10);
+    }
+
+    #[test]
+    fn clamps_page_and_handles_empty() {
+        assert_eq!(Pager::new(25, 10, 9).page(), 2);
+        let empty = Pager::new(0, 10, 3);
+        assert_eq!((empty.pages(), empty.page(), empty.range()), (1, 0, 0..0));
+        assert_eq!(Pager::new(5, 0, 0).pages(), 5);
+    }
+
+    #[test]
+    fn next_and_prev_wrap() {
+        let p = Pager::new(30, 10, 2);
+        assert_eq!((p.next(), p.prev()), (0, 1));
+        let first = Pager::new(30, 10, 0);
+        assert_eq!(first.prev(), 2);
+    }
+}
@@ -0,0 +1,165 @@
+//! Color swatch reference (`#palette`).
+//!
+//! Shows a sample of the RGB 5-5-5 space as swatches labelled with their
+//! hex value. Each channel steps through 0, 8, 16, 24 and 31, giving one
+//! row per red/green pair with blue varying across it. Rows that do not fit
+//! on screen go onto further pages. Changing page slides the rows across
+//! unless `#config pageslide` is off.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::colors::{self, irgb};
+use crate::layout::{self, Rect, Screen};
+use crate::pager::{self, Pager, Slide};
+use crate::render::{DrawList, Layer, TextFlags};
+use crate::FRAME_COUNT;
+
+/// Channel values sampled for each of red, green and blue.
+const STEPS: [u16; 5] = [0, 8, 16, 24, 31];
+
+const SWATCH: i32 = 12;
+const CELL_W: i32 = 46;
+const CELL_H: i32 = 16;
+const TITLE_H: i32 = 20;
+const PADDING: i32 = 4;
+
+/// Show the palette (`#palette`).
+pub static SHOW: AtomicBool = AtomicBool::new(false);
+
+static PAGE: AtomicUsize = AtomicUsize::new(0);
+
+static SLIDING: Mutex<Option<Slide>> = Mutex::new(None);
+
+/// One row per red/green pair, blue increasing left to right.
+fn rows() -> Vec<[u16; STEPS.len()]> {
+    let mut rows = Vec::new();
+    for r in STEPS {
+        for g in STEPS {
+            rows.push(STEPS.map(|b| irgb(r, g, b)));
+        }
+    }
+    rows
+}
+
+/// Rows per page that fit on `screen`, at least one.
+fn rows_per_page(screen: Screen) -> usize {
+    ((screen.height() - TITLE_H - 2 * PADDING) / CELL_H).max(1) as usize
+}
+
+fn pager(screen: Screen) -> Pager {
+    Pager::new(rows().len(), rows_per_page(screen), PAGE.load(Ordering::Relaxed))
+}
+
+fn set_sliding(slide: Option<Slide>) {
+    *SLIDING.lock().unwrap_or_else(|e| e.into_inner()) = slide;
+}
+
+fn turn_page(forward: bool) {
+    let pager = pager(Screen::current());
+    let to = if forward { pager.next() } else { pager.prev() };
+    if to != pager.page() && pager::SLIDE.load(Ordering::Relaxed) {
+        let start_frame = FRAME_COUNT.load(Ordering::Relaxed);
+        set_sliding(Some(Slide { from: pager.page(), forward, start_frame }));
+    }
+    PAGE.store(to, Ordering::Relaxed);
+}
+
+pub fn next_page() {
+    turn_page(true);
+}
+
+pub fn prev_page() {
+    turn_page(false);
+}
+
+pub fn reset() {
+    SHOW.store(false, Ordering::Relaxed);
+    PAGE.store(0, Ordering::Relaxed);
+    set_sliding(None);
+}
+
+fn draw_rows(list: &mut DrawList, rows: &[[u16; STEPS.len()]], x: i32, y: i32, text_color: u16) {
+    let mut row_y = y;
+    for row in rows {
+        for (col, color) in row.iter().enumerate() {
+            let cx = x + col as i32 * CELL_W;
+            list.rect(cx, row_y, cx + SWATCH, row_y + SWATCH, *color);
+            list.text(cx + SWATCH + 2, row_y, text_color, TextFlags::NONE, &format!("{:04X}", color));
+        }
+        row_y += CELL_H;
+    }
+}
+
+/// Records the swatch panel on the popup layer, if shown. Leaves the list on
+/// the standard layer.
+pub fn build(list: &mut DrawList) {
+    if !SHOW.load(Ordering::Relaxed) {
+        return;
+    }
+    let theme = colors::theme();
+    let screen = Screen::current();
+    let pager = pager(screen);
+    let rows = rows();
+    list.set_layer(Layer::Popup);
+
+    let frame = FRAME_COUNT.load(Ordering::Relaxed);
+    let slide = *SLIDING.lock().unwrap_or_else(|e| e.into_inner());
+    let content_w = STEPS.len() as i32 * CELL_W;
+    // The page being slid away from, and the old and new page offsets.
+    let sliding = slide.and_then(|s| {
+        let offsets = s.offsets(frame, content_w)?;
+        Some((Pager::new(pager.items, pager.per_page, s.from), offsets))
+    });
+    if slide.is_some() && sliding.is_none() {
+        set_sliding(None);
+    }
+
+    let mut shown_rows = pager.range().len();
+    if let Some((from, _)) = sliding {
+        shown_rows = shown_rows.max(from.range().len());
+    }
+    let w = content_w + 2 * PADDING;
+    let h = TITLE_H + shown_rows as i32 * CELL_H + PADDING;
+    let (x, y) = layout::fraction_position(screen, 50, 50, w, h);
+
+    list.rect(x, y, x + w, y + h, theme.background);
+    list.frame(x, y, x + w, y + h, theme.border);
+    let title = format!("Palette {}/{} (#palette next)", pager.page() + 1, pager.pages());
+    list.text(x + PADDING, y + PADDING, theme.title, TextFlags::NONE, &title);
+
+    let (cx, cy) = (x + PADDING, y + TITLE_H);
+    match sliding {
+        Some((from, (old_dx, new_dx))) => {
+            list.set_clip(Some(Rect { x: cx, y: cy, w: content_w, h: h - TITLE_H }));
+            draw_rows(list, &rows[from.range()], cx + old_dx, cy, theme.text);
+            draw_rows(list, &rows[pager.range()], cx + new_dx, cy, theme.text);
+            list.set_clip(None);
+        }
+        None => draw_rows(list, &rows[pager.range()], cx, cy, theme.text),
+    }
+    list.set_layer(Layer::Standard);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_every_channel_step() {
+        let rows = rows();
+        assert_eq!(rows.len(), 25);
+        assert_eq!(rows[0], [0x0000, 0x0008, 0x0010, 0x0018, 0x001F]);
+        assert_eq!(*rows.last().unwrap().last().unwrap(), 0x7FFF);
+    }
+
+    #[test]
+    fn small_screens_page_the_rows() {
+        let screen = Screen { left: 0, top: 0, right: 800, bottom: 200 };
+        let pager = Pager::new(rows().len(), rows_per_page(screen), 0);
+        assert_eq!(rows_per_page(screen), 10);
+        assert_eq!(pager.pages(), 3);
+        let tiny = Screen { left: 0, top: 0, right: 10, bottom: 10 };
+        assert_eq!(rows_per_page(tiny), 1);
+    }
+}
@@ -0,0 +1,137 @@
+//! Named overlay layouts (`#preset`).
+//!
+//! A preset is a copy of the overlay-related settings in [`KEYS`], taken by
+//! `#preset save` and put back by `#preset load`. Presets are stored in
+//! `rustmod.cfg` after the live settings, each under a `[preset <name>]`
+//! line, in the same `key = value` form.
+
+use std::sync::Mutex;
+
+/// Settings a preset captures: where the panel sits and how it and the
+/// other HUD elements look.
+pub const KEYS: &[&str] = &[
+    "overlay",
+    "overlay_x",
+    "overlay_y",
+    "overlay_abs",
+    "overlay_anchor",
+    "overlay_lines",
+    "overlay_verbose",
+    "overlay_border",
+    "overlay_layer",
+    "overlay_max_lines",
+    "combat_layout",
+    "combat_theme",
+    "zero_pad",
+    "rainbow",
+    "timer_arcs",
+    "status_bar",
+    "low_hp_percent",
+    "mana_threshold",
+    "blink_speed",
+    "grid",
+];
+
+/// Presets that can be saved.
+pub const MAX_PRESETS: usize = 16;
+
+/// Longest preset name.
+pub const MAX_NAME: usize = 24;
+
+/// Preset names are 1 to [`MAX_NAME`] letters, digits, `-` or `_`.
+pub fn valid_name(name: &str) -> bool {
+    (1..=MAX_NAME).contains(&name.len()) && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// Setting keys and values, in file order.
+pub type Entries = Vec<(String, String)>;
+
+#[derive(Debug, Default)]
+pub struct Presets {
+    list: Vec<(String, Entries)>,
+}
+
+impl Presets {
+    pub const fn new() -> Self {
+        Self { list: Vec::new() }
+    }
+
+    /// Stores `entries` as preset `name`, replacing any preset with that
+    /// name in place.
+    pub fn set(&mut self, name: &str, entries: Entries) {
+        match self.list.iter_mut().find(|(n, _)| n == name) {
+            Some((_, old)) => *old = entries,
+            None => self.list.push((name.to_string(), entries)),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Entries> {
+        self.list.iter().find(|(n, _)| n == name).map(|(_, e)| e)
+    }
+
+    /// Deletes preset `name`. Returns false if there was none.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.list.len();
+        self.list.retain(|(n, _)| n != name);
+        self.list.len() != before
+    }
+
+    /// Presets in the order they were first saved.
+    pub fn iter(&self) -> impl Iterator<Item = &(String, Entries)> {
+        self.list.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+    }
+}
+
+static PRESETS: Mutex<Presets> = Mutex::new(Presets::new());
+
+pub fn with_presets<R>(f: impl FnOnce(&mut Presets) -> R) -> R {
+    let mut guard = PRESETS.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut guard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(x: &str) -> Entries {
+        vec![("overlay_x".to_string(), x.to_string())]
+    }
+
+    #[test]
+    fn names_are_short_and_plain() {
+        assert!(valid_name("farming"));
+        assert!(valid_name("raid_2-alt"));
+        assert!(!valid_name(""));
+        assert!(!valid_name("two words"));
+        assert!(!valid_name("[x]"));
+        assert!(!valid_name(&"a".repeat(MAX_NAME + 1)));
+    }
+
+    #[test]
+    fn saving_again_replaces_in_place() {
+        let mut presets = Presets::new();
+        presets.set("farming", entries("10"));
+        presets.set("raiding", entries("20"));
+        presets.set("farming", entries("30"));
+        assert_eq!(presets.len(), 2);
+        assert_eq!(presets.get("farming"), Some(&entries("30")));
+        assert_eq!(presets.iter().next().map(|(n, _)| n.as_str()), Some("farming"));
+    }
+
+    #[test]
+    fn deleting_a_missing_preset_is_reported() {
+        let mut presets = Presets::new();
+        presets.set("farming", entries("10"));
+        assert!(!presets.remove("raiding"));
+        assert!(presets.remove("farming"));
+        assert!(presets.get("farming").is_none());
+    }
+}
@@ -0,0 +1,73 @@
+//! Stat efficiency ratios (`#ratios`).
+
+use std::ffi::c_int;
+
+/// The stats the ratios are computed from.
+#[derive(Clone, Copy, Debug)]
+pub struct Stats {
+    pub max_hp: c_int,
+    pub max_mana: c_int,
+    pub level: c_int,
+    pub strength: c_int,
+    pub intelligence: c_int,
+    pub wisdom: c_int,
+}
+
+/// `num / den`, or `None` when `den` is zero.
+pub fn ratio(num: c_int, den: c_int) -> Option<f64> {
+    (den != 0).then(|| num as f64 / den as f64)
+}
+
+/// Labelled ratios in display order.
+pub fn ratios(s: &Stats) -> [(&'static str, Option<f64>); 5] {
+    [
+        ("HP per level", ratio(s.max_hp, s.level)),
+        ("Mana per level", ratio(s.max_mana, s.level)),
+        ("HP per STR", ratio(s.max_hp, s.strength)),
+        ("Mana per INT", ratio(s.max_mana, s.intelligence)),
+        ("Mana per WIS", ratio(s.max_mana, s.wisdom)),
+    ]
+}
+
+/// One `label: 12.34` line per ratio, `n/a` where the divisor is zero.
+pub fn lines(s: &Stats) -> Vec<String> {
+    ratios(s)
+        .iter()
+        .map(|(label, r)| match r {
+            Some(r) => format!("{}: {:.2}", label, r),
+            None => format!("{}: n/a", label),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ratio_guards_zero_divisor() {
+        assert_eq!(ratio(10, 4), Some(2.5));
+        assert_eq!(ratio(10, 0), None);
+        assert_eq!(ratio(0, 3), Some(0.0));
+    }
+
+    #[test]
+    fn lines_format_two_decimals() {
+        let stats = Stats { max_hp: 500, max_mana: 200, level: 3, strength: 20, intelligence: 30, wisdom: 0 };
+        assert_eq!(
+            lines(&stats),
+            [
+                "HP per level: 166.67",
+                "Mana per level: 66.67",
+                "HP per STR: 25.00",
+                "Mana per INT: 6.67",
+                "Mana per WIS: n/a",
+            ]
+        );
+    }
+
+    #[test]
+    fn extremes_do_not_overflow() {
+        assert_eq!(ratio(c_int::MIN, -1), Some(2147483648.0));
+    }
+}
@@ -0,0 +1,605 @@
+//! Deferred drawing.
+//!
+//! Overlay code records primitives into a [`DrawList`], which is then flushed
+//! to a [`Canvas`]. Normally that is [`HostCanvas`], which forwards to the
+//! client's renderer; [`NullCanvas`] discards everything and is used to time
+//! the overlay without touching the screen.
+//!
+//! Every primitive is recorded on a [`Layer`]. Flushing draws the layers from
+//! lowest to highest, so elements on a higher layer are never covered by
+//! elements on a lower one regardless of which was recorded first. Within a
+//! layer, primitives draw in the order they were recorded.
+//!
+//! The client cannot clip, so an optional clip rectangle is applied while
+//! recording: rectangles are cut to it, and lines and text that do not fit
+//! entirely inside it are dropped.
+//!
+//! Text is laid out before it is drawn, from an average glyph advance learnt
+//! from the widths `render_text` reports (see [`TextMetrics`]). If the client
+//! keeps reporting no width, layout falls back to a fixed estimate.
+
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::ops::{BitOr, BitOrAssign};
+use std::sync::Mutex;
+
+use crate::layout::Rect;
+use crate::{c_text, log, render_line, render_rect, render_text};
+
+/// Something primitives can be drawn onto.
+pub trait Canvas {
+    fn rect(&mut self, sx: c_int, sy: c_int, ex: c_int, ey: c_int, color: u16);
+    fn line(&mut self, fx: c_int, fy: c_int, tx: c_int, ty: c_int, color: u16);
+    /// Draws text and returns its width in pixels.
+    fn text(&mut self, x: c_int, y: c_int, color: u16, flags: c_int, text: *const c_char) -> c_int;
+}
+
+/// Draws through the client's render functions.
+pub struct HostCanvas;
+
+impl Canvas for HostCanvas {
+    fn rect(&mut self, sx: c_int, sy: c_int, ex: c_int, ey: c_int, color: u16) {
+        unsafe { render_rect(sx, sy, ex, ey, color) }
+    }
+
+    fn line(&mut self, fx: c_int, fy: c_int, tx: c_int, ty: c_int, color: u16) {
+        unsafe { render_line(fx, fy, tx, ty, color) }
+    }
+
+    fn text(&mut self, x: c_int, y: c_int, color: u16, flags: c_int, text: *const c_char) -> c_int {
+        unsafe { render_text(x, y, color, flags, text) }
+    }
+}
+
+/// Discards all drawing.
+pub struct NullCanvas;
+
+impl Canvas for NullCanvas {
+    fn rect(&mut self, _sx: c_int, _sy: c_int, _ex: c_int, _ey: c_int, _color: u16) {}
+
+    fn line(&mut self, _fx: c_int, _fy: c_int, _tx: c_int, _ty: c_int, _color: u16) {}
+
+    fn text(&mut self, _x: c_int, _y: c_int, _color: u16, _flags: c_int, _text: *const c_char) -> c_int {
+        0
+    }
+}
+
+/// Approximate advance of one glyph in the client's default font.
+const GLYPH_W: c_int = 6;
+
+/// Consecutive `render_text` calls reporting no width after which the
+/// reported widths are no longer trusted.
+const FAILURE_LIMIT: u32 = 16;
+
+/// Text widths reported by the client, averaged into a glyph advance.
+#[derive(Debug, Default)]
+pub struct TextMetrics {
+    chars: u64,
+    width: u64,
+    failures: u32,
+    failed: bool,
+}
+
+impl TextMetrics {
+    pub const fn new() -> Self {
+        Self { chars: 0, width: 0, failures: 0, failed: false }
+    }
+
+    /// Records that drawing `chars` characters reported `width` pixels.
+    /// After [`FAILURE_LIMIT`] reports in a row of zero or less, switches
+    /// to the fixed estimate for good and logs it.
+    pub fn observe(&mut self, chars: usize, width: c_int) {
+        if self.failed || chars == 0 {
+            return;
+        }
+        if width > 0 {
+            self.failures = 0;
+            self.chars += chars as u64;
+            self.width += width as u64;
+            return;
+        }
+        self.failures += 1;
+        if self.failures >= FAILURE_LIMIT {
+            *self = Self { failed: true, ..Self::new() };
+            log(&format!(
+                "render_text reported no width {} times in a row, using fixed-width text layout",
+                FAILURE_LIMIT
+            ));
+        }
+    }
+
+    /// Estimated width of `chars` characters.
+    pub fn width(&self, chars: usize) -> c_int {
+        match self.chars {
+            0 => chars as c_int * GLYPH_W,
+            measured => (chars as u64 * self.width / measured) as c_int,
+        }
+    }
+}
+
+static METRICS: Mutex<TextMetrics> = Mutex::new(TextMetrics::new());
+
+/// Forgets measured widths and any fallback.
+pub fn reset() {
+    *METRICS.lock().unwrap_or_else(|e| e.into_inner()) = TextMetrics::new();
+}
+
+/// Width of `text` in the default font, for layout before anything is drawn.
+///
+/// The client only reports a text's width after drawing it, so this is an
+/// estimate from the widths seen so far, or from a fixed glyph advance.
+pub fn text_width(text: &str) -> c_int {
+    METRICS.lock().unwrap_or_else(|e| e.into_inner()).width(text.chars().count())
+}
+
+/// Passes drawing through to `canvas`, feeding the text widths it reports
+/// into `metrics`.
+pub struct Measuring<'a, C> {
+    canvas: C,
+    metrics: &'a mut TextMetrics,
+}
+
+impl<'a, C: Canvas> Measuring<'a, C> {
+    pub fn new(canvas: C, metrics: &'a mut TextMetrics) -> Self {
+        Self { canvas, metrics }
+    }
+}
+
+impl<C: Canvas> Canvas for Measuring<'_, C> {
+    fn rect(&mut self, sx: c_int, sy: c_int, ex: c_int, ey: c_int, color: u16) {
+        self.canvas.rect(sx, sy, ex, ey, color);
+    }
+
+    fn line(&mut self, fx: c_int, fy: c_int, tx: c_int, ty: c_int, color: u16) {
+        self.canvas.line(fx, fy, tx, ty, color);
+    }
+
+    fn text(&mut self, x: c_int, y: c_int, color: u16, flags: c_int, text: *const c_char) -> c_int {
+        let width = self.canvas.text(x, y, color, flags, text);
+        let chars = unsafe { CStr::from_ptr(text) }.to_string_lossy().chars().count();
+        self.metrics.observe(chars, width);
+        width
+    }
+}
+
+/// Draws `list` on screen, learning text widths as it goes.
+pub fn flush_to_host(list: &DrawList) {
+    let mut metrics = METRICS.lock().unwrap_or_else(|e| e.into_inner());
+    list.flush(&mut Measuring::new(HostCanvas, &mut metrics));
+}
+
+/// Flags for `render_text`, as defined by the client's renderer.
+///
+/// Only the flags the mod draws with are named, with the values of the
+/// matching `DD_*` defines in the client's `dd.h`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TextFlags(c_int);
+
+impl TextFlags {
+    /// Left-aligned at `x`, normal size, no effects (`DD_LEFT`).
+    pub const NONE: TextFlags = TextFlags(0);
+    /// Right-align the text so it ends at `x` (`DD_RIGHT`).
+    pub const RIGHT: TextFlags = TextFlags(2);
+    /// Use the small font (`DD_SMALL`).
+    pub const SMALL: TextFlags = TextFlags(8);
+
+    pub const fn bits(self) -> c_int {
+        self.0
+    }
+
+    pub const fn contains(self, other: TextFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for TextFlags {
+    type Output = TextFlags;
+
+    fn bitor(self, rhs: TextFlags) -> TextFlags {
+        TextFlags(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for TextFlags {
+    fn bitor_assign(&mut self, rhs: TextFlags) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Drawing layers, lowest first.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub enum Layer {
+    /// Guides and decorations behind everything else.
+    Background,
+    /// HUD panels such as the vitals overlay.
+    #[default]
+    Standard,
+    /// Menus and other transient UI.
+    Popup,
+    /// Elements that must never be covered.
+    Top,
+    /// A modal menu and its backdrop, above even [`Layer::Top`]. Not
+    /// selectable for the panel, so it is not in [`Layer::ALL`].
+    Modal,
+}
+
+impl Layer {
+    /// The layers the panel can be put on.
+    pub const ALL: [Layer; 4] = [Layer::Background, Layer::Standard, Layer::Popup, Layer::Top];
+
+    /// Every layer, in drawing order.
+    const DRAW_ORDER: [Layer; 5] = [Layer::Background, Layer::Standard, Layer::Popup, Layer::Top, Layer::Modal];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|layer| layer.name() == s)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Layer::Background => "background",
+            Layer::Standard => "standard",
+            Layer::Popup => "popup",
+            Layer::Top => "top",
+            Layer::Modal => "modal",
+        }
+    }
+
+    pub fn from_u8(v: u8) -> Self {
+        match v {
+            0 => Layer::Background,
+            2 => Layer::Popup,
+            3 => Layer::Top,
+            4 => Layer::Modal,
+            _ => Layer::Standard,
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Layer::Background => 0,
+            Layer::Standard => 1,
+            Layer::Popup => 2,
+            Layer::Top => 3,
+            Layer::Modal => 4,
+        }
+    }
+}
+
+/// Line segments in a full circle drawn by [`DrawList::arc`].
+const ARC_SEGMENTS: f32 = 32.0;
+
+/// Points along the arc of radius `radius` around (`cx`, `cy`) from
+/// `start_frac` to `end_frac` of a turn, measured clockwise from the top.
+/// An end before the start wraps through the top; equal fractions give no
+/// points, and spans longer than a turn stop after one.
+pub fn arc_points(cx: c_int, cy: c_int, radius: c_int, start_frac: f32, end_frac: f32) -> Vec<(c_int, c_int)> {
+    let mut span = end_frac - start_frac;
+    if span < 0.0 {
+        span += 1.0;
+    }
+    let span = span.min(1.0);
+    if span <= 0.0 {
+        return Vec::new();
+    }
+    let segments = (span * ARC_SEGMENTS).ceil().max(1.0) as usize;
+    let mut points: Vec<(c_int, c_int)> = (0..=segments)
+        .map(|i| {
+            let angle = (start_frac + span * i as f32 / segments as f32) * std::f32::consts::TAU;
+            let x = cx as f32 + radius as f32 * angle.sin();
+            let y = cy as f32 - radius as f32 * angle.cos();
+            (x.round() as c_int, y.round() as c_int)
+        })
+        .collect();
+    // Small radii round neighbouring points together.
+    points.dedup();
+    points
+}
+
+#[derive(Debug, Clone)]
+pub enum DrawCmd {
+    Rect { sx: c_int, sy: c_int, ex: c_int, ey: c_int, color: u16 },
+    Line { fx: c_int, fy: c_int, tx: c_int, ty: c_int, color: u16 },
+    Text { x: c_int, y: c_int, color: u16, flags: TextFlags, text: CString },
+}
+
+/// Primitives grouped by layer; see the module docs for drawing order.
+#[derive(Debug, Default)]
+pub struct DrawList {
+    cmds: Vec<(Layer, DrawCmd)>,
+    layer: Layer,
+    clip: Option<Rect>,
+}
+
+impl DrawList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the layer for primitives recorded from now on.
+    pub fn set_layer(&mut self, layer: Layer) {
+        self.layer = layer;
+    }
+
+    /// Clips primitives recorded from now on to `clip`, or stops clipping.
+    pub fn set_clip(&mut self, clip: Option<Rect>) {
+        self.clip = clip;
+    }
+
+    fn push(&mut self, cmd: DrawCmd) {
+        self.cmds.push((self.layer, cmd));
+    }
+
+    /// True if the point is inside the clip rectangle, or nothing clips.
+    fn visible(&self, x: c_int, y: c_int) -> bool {
+        self.clip.map_or(true, |c| x >= c.x && x <= c.x + c.w && y >= c.y && y <= c.y + c.h)
+    }
+
+    pub fn rect(&mut self, sx: c_int, sy: c_int, ex: c_int, ey: c_int, color: u16) {
+        if let Some(c) = self.clip {
+            let (sx, sy, ex, ey) = (sx.max(c.x), sy.max(c.y), ex.min(c.x + c.w), ey.min(c.y + c.h));
+            if sx < ex && sy < ey {
+                self.push(DrawCmd::Rect { sx, sy, ex, ey, color });
+            }
+            return;
+        }
+        self.push(DrawCmd::Rect { sx, sy, ex, ey, color });
+    }
+
+    pub fn line(&mut self, fx: c_int, fy: c_int, tx: c_int, ty: c_int, color: u16) {
+        if self.visible(fx, fy) && self.visible(tx, ty) {
+            self.push(DrawCmd::Line { fx, fy, tx, ty, color });
+        }
+    }
+
+    /// Outlines a rectangle with four lines.
+    pub fn frame(&mut self, sx: c_int, sy: c_int, ex: c_int, ey: c_int, color: u16) {
+        self.line(sx, sy, ex, sy, color);
+        self.line(sx, ey, ex, ey, color);
+        self.line(sx, sy, sx, ey, color);
+        self.line(ex, sy, ex, ey, color);
+    }
+
+    /// Approximates an arc with short lines; see [`arc_points`].
+    pub fn arc(&mut self, cx: c_int, cy: c_int, radius: c_int, start_frac: f32, end_frac: f32, color: u16) {
+        for pair in arc_points(cx, cy, radius, start_frac, end_frac).windows(2) {
+            self.line(pair[0].0, pair[0].1, pair[1].0, pair[1].1, color);
+        }
+    }
+
+    /// Records text. When clipping, the text's estimated extent (see
+    /// [`text_width`]) must fit inside the clip rectangle.
+    pub fn text(&mut self, x: c_int, y: c_int, color: u16, flags: TextFlags, text: &str) {
+        if self.clip.is_some() {
+            let w = text_width(text);
+            let left = if flags.contains(TextFlags::RIGHT) { x - w } else { x };
+            if !self.visible(left, y) || !self.visible(left + w, y) {
+                return;
+            }
+        }
+        self.push(DrawCmd::Text { x, y, color, flags, text: c_text(text) });
+    }
+
+    /// Draws every recorded primitive onto `canvas`, layer by layer.
+    pub fn flush(&self, canvas: &mut impl Canvas) {
+        for layer in Layer::DRAW_ORDER {
+            let cmds = self.cmds.iter().filter(|(l, _)| *l == layer).map(|(_, cmd)| cmd);
+            for cmd in cmds {
+                draw(canvas, cmd);
+            }
+        }
+    }
+}
+
+fn draw(canvas: &mut impl Canvas, cmd: &DrawCmd) {
+    match cmd {
+        DrawCmd::Rect { sx, sy, ex, ey, color } => canvas.rect(*sx, *sy, *ex, *ey, *color),
+        DrawCmd::Line { fx, fy, tx, ty, color } => canvas.line(*fx, *fy, *tx, *ty, *color),
+        DrawCmd::Text { x, y, color, flags, text } => {
+            canvas.text(*x, *y, *color, flags.bits(), text.as_ptr());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Recorder;
+
+    #[test]
+    fn flush_replays_in_order() {
+        let mut list = DrawList::new();
+        list.rect(0, 0, 10, 10, 0);
+        list.frame(0, 0, 10, 10, 0);
+        list.text(2, 3, 0, TextFlags::NONE, "hi");
+
+        let mut canvas = Recorder::default();
+        list.flush(&mut canvas);
+        assert_eq!(
+            canvas.log,
+            [
+                "rect 0 0 10 10",
+                "line 0 0 10 0",
+                "line 0 10 10 10",
+                "line 0 0 0 10",
+                "line 10 0 10 10",
+                "text 2 3 hi",
+            ]
+        );
+    }
+
+    /// A client whose text rendering always reports `width`.
+    struct FixedWidth(c_int);
+
+    impl Canvas for FixedWidth {
+        fn rect(&mut self, _sx: c_int, _sy: c_int, _ex: c_int, _ey: c_int, _color: u16) {}
+
+        fn line(&mut self, _fx: c_int, _fy: c_int, _tx: c_int, _ty: c_int, _color: u16) {}
+
+        fn text(&mut self, _x: c_int, _y: c_int, _color: u16, _flags: c_int, _text: *const c_char) -> c_int {
+            self.0
+        }
+    }
+
+    fn draw_text(metrics: &mut TextMetrics, reported: c_int, times: u32) {
+        let mut list = DrawList::new();
+        for _ in 0..times {
+            list.text(0, 0, 0, TextFlags::NONE, "abcd");
+        }
+        list.flush(&mut Measuring::new(FixedWidth(reported), metrics));
+    }
+
+    #[test]
+    fn reported_widths_replace_the_estimate() {
+        let mut metrics = TextMetrics::new();
+        assert_eq!(metrics.width(10), 10 * GLYPH_W);
+        draw_text(&mut metrics, 32, 3);
+        assert_eq!(metrics.width(10), 80);
+    }
+
+    #[test]
+    fn repeated_failures_fall_back_once() {
+        crate::testing::take_log();
+        let mut metrics = TextMetrics::new();
+        draw_text(&mut metrics, 32, 1);
+        draw_text(&mut metrics, 0, FAILURE_LIMIT - 1);
+        assert_eq!(metrics.width(10), 80);
+        assert!(crate::testing::take_log().is_empty());
+
+        draw_text(&mut metrics, -1, 1);
+        assert_eq!(metrics.width(10), 10 * GLYPH_W);
+        draw_text(&mut metrics, 0, 2 * FAILURE_LIMIT);
+        draw_text(&mut metrics, 99, 1);
+        assert_eq!(metrics.width(10), 10 * GLYPH_W);
+        assert_eq!(crate::testing::take_log().len(), 1);
+    }
+
+    #[test]
+    fn a_success_clears_the_failure_count() {
+        let mut metrics = TextMetrics::new();
+        for _ in 0..3 {
+            draw_text(&mut metrics, 0, FAILURE_LIMIT - 1);
+            draw_text(&mut metrics, 24, 1);
+        }
+        assert_eq!(metrics.width(4), 24);
+    }
+
+    #[test]
+    fn higher_layers_draw_last() {
+        let mut list = DrawList::new();
+        list.set_layer(Layer::Top);
+        list.rect(1, 1, 1, 1, 0);
+        list.set_layer(Layer::Standard);
+        list.rect(2, 2, 2, 2, 0);
+        list.set_layer(Layer::Background);
+        list.rect(3, 3, 3, 3, 0);
+        list.set_layer(Layer::Standard);
+        list.rect(4, 4, 4, 4, 0);
+
+        let mut canvas = Recorder::default();
+        list.flush(&mut canvas);
+        assert_eq!(canvas.log, ["rect 3 3 3 3", "rect 2 2 2 2", "rect 4 4 4 4", "rect 1 1 1 1"]);
+    }
+
+    #[test]
+    fn layer_names_round_trip() {
+        for layer in Layer::ALL {
+            assert_eq!(Layer::parse(layer.name()), Some(layer));
+        }
+        for layer in Layer::DRAW_ORDER {
+            assert_eq!(Layer::from_u8(layer.to_u8()), layer);
+        }
+        assert_eq!(Layer::parse("nope"), None);
+    }
+
+    #[test]
+    fn text_flags_combine() {
+        let flags = TextFlags::NONE | TextFlags::SMALL;
+        assert_eq!(flags.bits(), 8);
+        assert!(flags.contains(TextFlags::SMALL));
+        assert!(!flags.contains(TextFlags::RIGHT));
+
+        let mut flags = TextFlags::RIGHT;
+        flags |= TextFlags::SMALL;
+        assert_eq!(flags.bits(), 10);
+        assert_eq!(TextFlags::default(), TextFlags::NONE);
+    }
+
+    #[test]
+    fn flush_passes_flag_bits() {
+        struct Flags(Vec<c_int>);
+        impl Canvas for Flags {
+            fn rect(&mut self, _sx: c_int, _sy: c_int, _ex: c_int, _ey: c_int, _color: u16) {}
+            fn line(&mut self, _fx: c_int, _fy: c_int, _tx: c_int, _ty: c_int, _color: u16) {}
+            fn text(&mut self, _x: c_int, _y: c_int, _color: u16, flags: c_int, _text: *const c_char) -> c_int {
+                self.0.push(flags);
+                0
+            }
+        }
+
+        let mut list = DrawList::new();
+        list.text(0, 0, 0, TextFlags::RIGHT | TextFlags::SMALL, "x");
+        let mut canvas = Flags(Vec::new());
+        list.flush(&mut canvas);
+        assert_eq!(canvas.0, [10]);
+    }
+
+    #[test]
+    fn clip_cuts_rects_and_drops_what_does_not_fit() {
+        let mut list = DrawList::new();
+        list.set_clip(Some(Rect { x: 10, y: 10, w: 100, h: 50 }));
+        list.rect(0, 0, 50, 50, 0);
+        list.rect(200, 0, 250, 50, 0);
+        list.line(20, 20, 30, 20, 0);
+        list.line(20, 20, 300, 20, 0);
+        list.text(20, 20, 0, TextFlags::NONE, "fits");
+        list.text(100, 20, 0, TextFlags::NONE, "too wide");
+        list.text(30, 20, 0, TextFlags::RIGHT, "left");
+        list.set_clip(None);
+        list.rect(200, 0, 250, 50, 0);
+
+        let mut canvas = Recorder::default();
+        list.flush(&mut canvas);
+        assert_eq!(
+            canvas.log,
+            ["rect 10 10 50 50", "line 20 20 30 20", "text 20 20 fits", "rect 200 0 250 50"]
+        );
+    }
+
+    #[test]
+    fn quarter_arc_runs_from_top_to_right() {
+        let points = arc_points(100, 100, 10, 0.0, 0.25);
+        assert_eq!(points.first(), Some(&(100, 90)));
+        assert_eq!(points.last(), Some(&(110, 100)));
+        assert_eq!(points.len(), 9);
+    }
+
+    #[test]
+    fn arc_wraps_through_the_top() {
+        let points = arc_points(0, 0, 10, 0.75, 0.25);
+        assert_eq!(points.first(), Some(&(-10, 0)));
+        assert_eq!(points.last(), Some(&(10, 0)));
+        assert!(points.contains(&(0, -10)));
+        assert!(arc_points(0, 0, 10, 0.5, 0.5).is_empty());
+    }
+
+    #[test]
+    fn arc_is_drawn_as_connected_lines() {
+        let mut list = DrawList::new();
+        list.arc(0, 0, 4, 0.0, 0.5, 0);
+        let mut canvas = Recorder::default();
+        list.flush(&mut canvas);
+        let points = arc_points(0, 0, 4, 0.0, 0.5);
+        assert_eq!(canvas.log.len(), points.len() - 1);
+        assert!(canvas.log[0].starts_with("line 0 -4 "));
+        assert!(canvas.log.last().unwrap().ends_with(" 0 4"));
+    }
+
+    #[test]
+    fn text_strips_interior_nul() {
+        let mut list = DrawList::new();
+        list.text(0, 0, 0, TextFlags::NONE, "a\0b");
+
+        let mut canvas = Recorder::default();
+        list.flush(&mut canvas);
+        assert_eq!(canvas.log, ["text 0 0 ab"]);
+    }
+}
@@ -0,0 +1,126 @@
+//! Fixed-capacity ring buffer shared by the history features.
+//!
+//! Once full, each push overwrites the oldest element. Storage grows up to
+//! `N` on demand, so an empty buffer costs nothing and `new` is `const`,
+//! which lets the buffers live in statics.
+
+/// The last `N` pushed values, iterated oldest to newest.
+#[derive(Clone, Debug)]
+pub struct RingBuffer<T, const N: usize> {
+    items: Vec<T>,
+    /// Index of the oldest element once the buffer is full.
+    head: usize,
+}
+
+impl<T, const N: usize> RingBuffer<T, N> {
+    pub const fn new() -> Self {
+        Self { items: Vec::new(), head: 0 }
+    }
+
+    /// Appends `value`, dropping the oldest element if the buffer is full.
+    pub fn push(&mut self, value: T) {
+        if N == 0 {
+            return;
+        }
+        if self.items.len() < N {
+            self.items.push(value);
+        } else {
+            self.items[self.head] = value;
+            self.head = (self.head + 1) % N;
+        }
+    }
+
+    /// Elements from oldest to newest.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> {
+        let (newer, older) = self.items.split_at(self.head);
+        older.iter().chain(newer)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.head = 0;
+    }
+}
+
+impl<T, const N: usize> Default for RingBuffer<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents<const N: usize>(ring: &RingBuffer<u32, N>) -> Vec<u32> {
+        ring.iter().copied().collect()
+    }
+
+    #[test]
+    fn partial_fill_keeps_push_order() {
+        let mut ring = RingBuffer::<u32, 4>::new();
+        assert!(ring.is_empty());
+        ring.push(1);
+        ring.push(2);
+        assert_eq!(contents(&ring), [1, 2]);
+        assert_eq!(ring.len(), 2);
+    }
+
+    #[test]
+    fn wraparound_drops_oldest_first() {
+        let mut ring = RingBuffer::<u32, 3>::new();
+        for v in 1..=3 {
+            ring.push(v);
+        }
+        assert_eq!(ring.len(), 3);
+        assert_eq!(contents(&ring), [1, 2, 3]);
+        ring.push(4);
+        assert_eq!(contents(&ring), [2, 3, 4]);
+        ring.push(5);
+        ring.push(6);
+        assert_eq!(contents(&ring), [4, 5, 6]);
+        ring.push(7);
+        assert_eq!(contents(&ring), [5, 6, 7]);
+        assert_eq!(ring.len(), 3);
+    }
+
+    #[test]
+    fn many_laps_keep_the_newest() {
+        let mut ring = RingBuffer::<u32, 5>::new();
+        for v in 0..1003 {
+            ring.push(v);
+        }
+        assert_eq!(contents(&ring), [998, 999, 1000, 1001, 1002]);
+        assert_eq!(ring.iter().next_back(), Some(&1002));
+    }
+
+    #[test]
+    fn clear_starts_over() {
+        let mut ring = RingBuffer::<u32, 2>::new();
+        for v in 0..5 {
+            ring.push(v);
+        }
+        ring.clear();
+        assert!(ring.is_empty());
+        ring.push(9);
+        ring.push(10);
+        ring.push(11);
+        assert_eq!(contents(&ring), [10, 11]);
+    }
+
+    #[test]
+    fn zero_capacity_holds_nothing() {
+        let mut ring = RingBuffer::<u32, 0>::new();
+        ring.push(1);
+        assert!(ring.is_empty());
+        assert_eq!(contents(&ring), []);
+    }
+}
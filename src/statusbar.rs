@@ -0,0 +1,129 @@
+//! Full-width status bar along the top edge (`#statusbar`).
+//!
+//! An always-on alternative to the panel: HP and mana as filled blocks
+//! showing their percentage, then level and gold, spread across the screen
+//! between the top corner dots. Labels are centred from their measured
+//! width and left out when a segment is too narrow for them.
+
+use std::ffi::c_int;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::colors;
+use crate::format::format_thousands;
+use crate::game;
+use crate::layout::{Rect, Screen};
+use crate::render::{self, DrawList, TextFlags};
+
+/// Height of the bar in pixels.
+const BAR_H: i32 = 14;
+
+/// Gap between segments.
+const GAP: i32 = 2;
+
+/// Show the status bar (`#statusbar`).
+pub static SHOW: AtomicBool = AtomicBool::new(false);
+
+/// Relative widths of the HP, mana, level and gold segments.
+const WEIGHTS: [i32; 4] = [3, 3, 1, 2];
+
+/// `value` as a percentage of `max`, clamped to `0..=100`, or `None` while
+/// the maximum is unknown.
+fn percent(value: c_int, max: c_int) -> Option<i32> {
+    (max > 0).then(|| (value.clamp(0, max) as i64 * 100 / max as i64) as i32)
+}
+
+/// Splits `left..right` into spans proportional to `weights`, `GAP` apart.
+/// Rounding leftovers go to the last span so the bar always ends at `right`.
+fn split(left: i32, right: i32, weights: &[i32]) -> Vec<(i32, i32)> {
+    let total: i32 = weights.iter().sum();
+    let usable = (right - left - GAP * (weights.len() as i32 - 1)).max(0);
+    let mut spans = Vec::with_capacity(weights.len());
+    let mut x = left;
+    for (i, weight) in weights.iter().enumerate() {
+        let end = if i + 1 == weights.len() { right } else { x + usable * weight / total };
+        spans.push((x, end.max(x)));
+        x = end + GAP;
+    }
+    spans
+}
+
+/// One segment: its text, and the share of it to fill with `fill`.
+struct Segment {
+    text: String,
+    fill: Option<(i32, u16)>,
+}
+
+fn segments() -> [Segment; 4] {
+    let theme = colors::theme();
+    let gauge = |label: &str, value, max, color| match percent(value, max) {
+        Some(pct) => Segment { text: format!("{} {}%", label, pct), fill: Some((pct, color)) },
+        None => Segment { text: format!("{} --", label), fill: None },
+    };
+    [
+        gauge("HP", game::hp(), game::max_hp(), theme.health),
+        gauge("MP", game::mana(), game::max_mana(), theme.mana),
+        Segment {
+            text: game::try_level().map_or("Lv --".to_string(), |level| format!("Lv {}", level)),
+            fill: None,
+        },
+        Segment { text: format!("Gold {}", format_thousands(game::gold())), fill: None },
+    ]
+}
+
+/// Records the bar along the top of `screen`, if shown.
+pub fn build(list: &mut DrawList, screen: Screen) {
+    if !SHOW.load(Ordering::Relaxed) {
+        return;
+    }
+    let theme = colors::theme();
+    let (top, bottom) = (screen.top, screen.top + BAR_H);
+    for ((x0, x1), segment) in split(screen.left, screen.right, &WEIGHTS).into_iter().zip(segments()) {
+        list.rect(x0, top, x1, bottom, theme.background);
+        if let Some((pct, color)) = segment.fill {
+            list.rect(x0, top, x0 + (x1 - x0) * pct / 100, bottom, color);
+        }
+        let w = render::text_width(&segment.text);
+        list.set_clip(Some(Rect { x: x0, y: top, w: x1 - x0, h: BAR_H }));
+        list.text((x0 + x1 - w) / 2, top + 2, theme.text, TextFlags::NONE, &segment.text);
+        list.set_clip(None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_clamps_and_needs_a_maximum() {
+        assert_eq!(percent(75, 100), Some(75));
+        assert_eq!(percent(150, 100), Some(100));
+        assert_eq!(percent(-3, 100), Some(0));
+        assert_eq!(percent(1, 3), Some(33));
+        assert_eq!(percent(5, 0), None);
+        assert_eq!(percent(c_int::MAX, c_int::MAX), Some(100));
+    }
+
+    #[test]
+    fn split_spans_the_whole_width() {
+        let spans = split(0, 800, &WEIGHTS);
+        assert_eq!(spans.len(), 4);
+        assert_eq!(spans[0].0, 0);
+        assert_eq!(spans[3].1, 800);
+        for pair in spans.windows(2) {
+            assert_eq!(pair[1].0 - pair[0].1, GAP);
+        }
+        assert_eq!(spans[0].1 - spans[0].0, 3 * (spans[2].1 - spans[2].0));
+    }
+
+    #[test]
+    fn split_respects_the_screen_origin() {
+        assert_eq!(split(100, 204, &[1, 1]), [(100, 151), (153, 204)]);
+    }
+
+    #[test]
+    fn narrow_screens_do_not_invert_spans() {
+        for (x0, x1) in split(0, 3, &WEIGHTS) {
+            assert!(x1 >= x0);
+        }
+    }
+}
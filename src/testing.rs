@@ -0,0 +1,206 @@
+//! Test doubles for the client's output functions.
+//!
+//! In test builds `chat` and `log` record into thread-local buffers instead
+//! of calling into the client, so tests can check what the mod reports to
+//! the user.
+//!
+//! [`MemFs`] stands in for the disk where file writes need to be observed,
+//! and [`Recorder`] for the screen where drawing order does.
+//!
+//! The client's functions and globals are defined here as well, so that
+//! code calling into the host can link and run in tests. They draw and
+//! print nothing, and the game looks like a fresh level 1 character on an
+//! 800x600 screen.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::ffi::{c_char, c_int, CStr};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::config::Fs;
+use crate::render::Canvas;
+use crate::{DOT_BR, V_MAX};
+
+thread_local! {
+    static CHAT: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    static LOG: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    static EXP2LEVEL: Cell<fn(c_int) -> c_int> = const { Cell::new(|_| 1) };
+}
+
+pub fn chat(text: &str) {
+    CHAT.with(|c| c.borrow_mut().push(text.to_string()));
+}
+
+pub fn log(text: &str) {
+    LOG.with(|l| l.borrow_mut().push(text.to_string()));
+}
+
+/// Returns and clears the chat lines written on this thread.
+pub fn take_chat() -> Vec<String> {
+    CHAT.with(|c| c.take())
+}
+
+/// Returns and clears the log lines written on this thread.
+pub fn take_log() -> Vec<String> {
+    LOG.with(|l| l.take())
+}
+
+/// Makes the client's `exp2level` answer with `f` on this thread.
+pub fn set_exp2level(f: fn(c_int) -> c_int) {
+    EXP2LEVEL.with(|e| e.set(f));
+}
+
+/// An in-memory filesystem. Clones share the same files, so a test can keep
+/// one handle to inspect what a store it handed the other one wrote.
+#[derive(Clone, Default)]
+pub struct MemFs {
+    files: Arc<Mutex<HashMap<PathBuf, String>>>,
+    read_only: bool,
+    unreadable: bool,
+}
+
+impl MemFs {
+    /// A filesystem on which every write fails.
+    pub fn read_only() -> Self {
+        Self { read_only: true, ..Self::default() }
+    }
+
+    /// A filesystem on which every read fails with a permission error.
+    pub fn unreadable() -> Self {
+        Self { unreadable: true, ..Self::default() }
+    }
+
+    pub fn file(&self, path: &Path) -> Option<String> {
+        self.files.lock().unwrap().get(path).cloned()
+    }
+}
+
+impl Fs for MemFs {
+    fn is_dir(&self, _path: &Path) -> bool {
+        true
+    }
+
+    fn create_dir_all(&mut self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        if self.unreadable {
+            return Err(io::ErrorKind::PermissionDenied.into());
+        }
+        self.file(path).ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+
+    fn write(&mut self, path: &Path, text: &str) -> io::Result<()> {
+        if self.read_only {
+            return Err(io::ErrorKind::PermissionDenied.into());
+        }
+        self.files.lock().unwrap().insert(path.to_path_buf(), text.to_string());
+        Ok(())
+    }
+
+    fn append(&mut self, path: &Path, text: &str) -> io::Result<()> {
+        if self.read_only {
+            return Err(io::ErrorKind::PermissionDenied.into());
+        }
+        self.files.lock().unwrap().entry(path.to_path_buf()).or_default().push_str(text);
+        Ok(())
+    }
+}
+
+/// A canvas that logs each primitive it is given as text.
+#[derive(Default)]
+pub struct Recorder {
+    pub log: Vec<String>,
+}
+
+impl Canvas for Recorder {
+    fn rect(&mut self, sx: c_int, sy: c_int, ex: c_int, ey: c_int, _color: u16) {
+        self.log.push(format!("rect {} {} {} {}", sx, sy, ex, ey));
+    }
+
+    fn line(&mut self, fx: c_int, fy: c_int, tx: c_int, ty: c_int, _color: u16) {
+        self.log.push(format!("line {} {} {} {}", fx, fy, tx, ty));
+    }
+
+    fn text(&mut self, x: c_int, y: c_int, _color: u16, _flags: c_int, text: *const c_char) -> c_int {
+        let text = unsafe { CStr::from_ptr(text) }.to_string_lossy();
+        self.log.push(format!("text {} {} {}", x, y, text));
+        0
+    }
+}
+
+// The client's exports. `note` and `addline` are variadic in the client;
+// these ignore everything after the format, which the C calling convention
+// allows.
+
+#[no_mangle]
+extern "C" fn note(_format: *const c_char) -> c_int {
+    0
+}
+
+#[no_mangle]
+extern "C" fn addline(_format: *const c_char) {}
+
+#[no_mangle]
+extern "C" fn render_rect(_sx: c_int, _sy: c_int, _ex: c_int, _ey: c_int, _color: u16) {}
+
+#[no_mangle]
+extern "C" fn render_line(_fx: c_int, _fy: c_int, _tx: c_int, _ty: c_int, _color: u16) {}
+
+#[no_mangle]
+extern "C" fn render_text(_sx: c_int, _sy: c_int, _color: u16, _flags: c_int, _text: *const c_char) -> c_int {
+    0
+}
+
+#[no_mangle]
+extern "C" fn dotx(didx: c_int) -> c_int {
+    if didx == DOT_BR {
+        800
+    } else {
+        0
+    }
+}
+
+#[no_mangle]
+extern "C" fn doty(didx: c_int) -> c_int {
+    if didx == DOT_BR {
+        600
+    } else {
+        0
+    }
+}
+
+#[no_mangle]
+extern "C" fn exp2level(val: c_int) -> c_int {
+    EXP2LEVEL.with(|e| e.get()(val))
+}
+
+#[allow(non_upper_case_globals)]
+mod globals {
+    use super::*;
+
+    #[no_mangle]
+    static hp: c_int = 0;
+    #[no_mangle]
+    static mana: c_int = 0;
+    #[no_mangle]
+    static gold: c_int = 0;
+    #[no_mangle]
+    static experience: c_int = 0;
+    #[no_mangle]
+    static value: [[c_int; V_MAX]; 2] = [[0; V_MAX]; 2];
+    #[no_mangle]
+    static username: [c_char; 40] = [0; 40];
+
+    #[no_mangle]
+    static whitecolor: u16 = 0xFFFF;
+    #[no_mangle]
+    static textcolor: u16 = 0xFFFF;
+    #[no_mangle]
+    static healthcolor: u16 = 0xF800;
+    #[no_mangle]
+    static manacolor: u16 = 0x001F;
+}
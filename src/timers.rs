@@ -0,0 +1,211 @@
+//! Countdown timers (`#timer`).
+//!
+//! Timers count down in ticks. When one reaches zero a chat alert is sent
+//! from `amod_tick`, so it fires even while the overlay is hidden, and the
+//! timer stays in the overlay flashing at 00:00 for [`FLASH_TICKS`] before
+//! it is removed.
+
+use std::sync::Mutex;
+
+use crate::TICKS_PER_SECOND;
+
+/// Most timers that can run at once.
+pub const MAX_TIMERS: usize = 8;
+
+/// Longest accepted countdown (24 hours).
+pub const MAX_SECONDS: u32 = 24 * 60 * 60;
+
+/// How long a finished timer keeps flashing in the overlay.
+pub const FLASH_TICKS: u32 = 3 * TICKS_PER_SECOND;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Timer {
+    pub label: String,
+    ends_at: u32,
+    /// Length of the countdown in ticks.
+    duration: u32,
+    finished: bool,
+}
+
+impl Timer {
+    /// Whole seconds left, rounded up so a timer reads 00:01 until it ends.
+    pub fn remaining_secs(&self, now: u32) -> u32 {
+        let left = self.ends_at.wrapping_sub(now) as i32;
+        if left <= 0 {
+            0
+        } else {
+            (left as u32 + TICKS_PER_SECOND - 1) / TICKS_PER_SECOND
+        }
+    }
+
+    /// Share of the countdown still to run, from 1 at the start to 0.
+    pub fn remaining_frac(&self, now: u32) -> f32 {
+        let left = (self.ends_at.wrapping_sub(now) as i32).max(0) as u32;
+        if self.duration == 0 {
+            0.0
+        } else {
+            left.min(self.duration) as f32 / self.duration as f32
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Timers {
+    list: Vec<Timer>,
+}
+
+impl Timers {
+    pub const fn new() -> Self {
+        Self { list: Vec::new() }
+    }
+
+    pub fn timers(&self) -> &[Timer] {
+        &self.list
+    }
+
+    /// Starts a countdown of `seconds` from tick `now`. Returns false if the
+    /// list is full.
+    pub fn add(&mut self, label: &str, seconds: u32, now: u32) -> bool {
+        if self.list.len() >= MAX_TIMERS {
+            return false;
+        }
+        let ticks = seconds.min(MAX_SECONDS) * TICKS_PER_SECOND;
+        self.list.push(Timer {
+            label: label.to_string(),
+            ends_at: now.wrapping_add(ticks),
+            duration: ticks,
+            finished: false,
+        });
+        true
+    }
+
+    /// Advances to tick `now`. Returns the labels of timers that just
+    /// finished and drops those that have flashed long enough.
+    pub fn tick(&mut self, now: u32) -> Vec<String> {
+        let mut finished = Vec::new();
+        for timer in &mut self.list {
+            if !timer.finished && timer.ends_at.wrapping_sub(now) as i32 <= 0 {
+                timer.finished = true;
+                finished.push(timer.label.clone());
+            }
+        }
+        self.list.retain(|t| !t.finished || (now.wrapping_sub(t.ends_at) as i32) < FLASH_TICKS as i32);
+        finished
+    }
+
+    /// Cancels every timer labelled `label`, ignoring ASCII case. Returns
+    /// how many were removed.
+    pub fn remove(&mut self, label: &str) -> usize {
+        let before = self.list.len();
+        self.list.retain(|t| !t.label.eq_ignore_ascii_case(label));
+        before - self.list.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+    }
+}
+
+static TIMERS: Mutex<Timers> = Mutex::new(Timers::new());
+
+pub fn with_timers<R>(f: impl FnOnce(&mut Timers) -> R) -> R {
+    let mut guard = TIMERS.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut guard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEC: u32 = TICKS_PER_SECOND;
+
+    #[test]
+    fn counts_down_in_whole_seconds() {
+        let mut t = Timers::new();
+        assert!(t.add("buff", 90, 100));
+        let timer = &t.timers()[0];
+        assert_eq!(timer.remaining_secs(100), 90);
+        assert_eq!(timer.remaining_secs(100 + SEC / 2), 90);
+        assert_eq!(timer.remaining_secs(100 + SEC), 89);
+        assert_eq!(timer.remaining_secs(100 + 90 * SEC - 1), 1);
+        assert_eq!(timer.remaining_secs(100 + 90 * SEC), 0);
+    }
+
+    #[test]
+    fn remaining_share_shrinks_to_zero() {
+        let mut t = Timers::new();
+        t.add("buff", 10, 0);
+        t.add("instant", 0, 0);
+        let (buff, instant) = (&t.timers()[0], &t.timers()[1]);
+        assert_eq!(buff.remaining_frac(0), 1.0);
+        assert_eq!(buff.remaining_frac(5 * SEC), 0.5);
+        assert_eq!(buff.remaining_frac(20 * SEC), 0.0);
+        assert_eq!(instant.remaining_frac(0), 0.0);
+    }
+
+    #[test]
+    fn reports_finish_once_then_flashes_and_expires() {
+        let mut t = Timers::new();
+        t.add("respawn", 2, 0);
+        assert!(t.tick(2 * SEC - 1).is_empty());
+        assert_eq!(t.tick(2 * SEC), ["respawn"]);
+        assert!(t.timers()[0].is_finished());
+        assert!(t.tick(2 * SEC + 1).is_empty());
+        assert_eq!(t.timers().len(), 1);
+        t.tick(2 * SEC + FLASH_TICKS);
+        assert!(t.timers().is_empty());
+    }
+
+    #[test]
+    fn finish_is_detected_after_skipped_ticks() {
+        let mut t = Timers::new();
+        t.add("late", 1, 0);
+        assert_eq!(t.tick(SEC + 5), ["late"]);
+    }
+
+    #[test]
+    fn concurrent_timers_finish_independently() {
+        let mut t = Timers::new();
+        t.add("a", 1, 0);
+        t.add("b", 3, 0);
+        assert_eq!(t.tick(SEC), ["a"]);
+        assert_eq!(t.tick(3 * SEC), ["b"]);
+    }
+
+    #[test]
+    fn list_is_capped() {
+        let mut t = Timers::new();
+        for i in 0..MAX_TIMERS {
+            assert!(t.add(&i.to_string(), 10, 0));
+        }
+        assert!(!t.add("extra", 10, 0));
+        t.clear();
+        assert!(t.add("extra", 10, 0));
+    }
+
+    #[test]
+    fn remove_cancels_by_label() {
+        let mut t = Timers::new();
+        t.add("Buff", 10, 0);
+        t.add("pot", 10, 0);
+        t.add("buff", 20, 0);
+        assert_eq!(t.remove("BUFF"), 2);
+        assert_eq!(t.remove("missing"), 0);
+        let labels: Vec<&str> = t.timers().iter().map(|t| t.label.as_str()).collect();
+        assert_eq!(labels, ["pot"]);
+    }
+
+    #[test]
+    fn survives_tick_wraparound() {
+        let mut t = Timers::new();
+        let start = u32::MAX - SEC / 2;
+        t.add("wrap", 1, start);
+        assert_eq!(t.timers()[0].remaining_secs(start), 1);
+        assert!(t.tick(start.wrapping_add(SEC - 1)).is_empty());
+        assert_eq!(t.tick(start.wrapping_add(SEC)), ["wrap"]);
+    }
+}
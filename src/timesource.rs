@@ -0,0 +1,122 @@
+//! Where time-based features read the time from (`#timesource`).
+//!
+//! Timers, gold rates, interval marks and overlay animation all measure in
+//! ticks taken from [`now_ticks`]. By default that is the tick counter,
+//! which advances once per `amod_tick`; the results depend only on how many
+//! ticks ran, so they are reproducible, but they run slow if the client
+//! ever drops ticks. The wall clock converts real elapsed time to ticks
+//! instead, which stays accurate under load but differs from run to run.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{TICKS_PER_SECOND, TICK_COUNT};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Source {
+    /// The tick counter: reproducible.
+    #[default]
+    Tick,
+    /// Real elapsed time since the mod loaded: accurate.
+    Wall,
+}
+
+impl Source {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "tick" => Some(Self::Tick),
+            "wall" => Some(Self::Wall),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Tick => "tick",
+            Self::Wall => "wall",
+        }
+    }
+
+    fn from_u8(v: u8) -> Self {
+        match v {
+            1 => Self::Wall,
+            _ => Self::Tick,
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::Tick => 0,
+            Self::Wall => 1,
+        }
+    }
+}
+
+static SOURCE: AtomicU8 = AtomicU8::new(0);
+
+/// When the wall clock started counting.
+static EPOCH: Mutex<Option<Instant>> = Mutex::new(None);
+
+pub fn source() -> Source {
+    Source::from_u8(SOURCE.load(Ordering::Relaxed))
+}
+
+pub fn set_source(source: Source) {
+    SOURCE.store(source.to_u8(), Ordering::Relaxed);
+}
+
+/// Back to the tick counter, with the wall clock restarting from now.
+pub fn reset() {
+    set_source(Source::Tick);
+    *EPOCH.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now());
+}
+
+/// The time in ticks under `source`, given the tick counter and the real
+/// time elapsed since the mod loaded.
+fn ticks_at(source: Source, tick_count: u32, elapsed: Duration) -> u64 {
+    match source {
+        Source::Tick => tick_count as u64,
+        Source::Wall => (elapsed.as_millis() * TICKS_PER_SECOND as u128 / 1000) as u64,
+    }
+}
+
+/// The current time in ticks from the selected source.
+pub fn now_ticks() -> u64 {
+    let elapsed = match *EPOCH.lock().unwrap_or_else(|e| e.into_inner()) {
+        Some(epoch) => epoch.elapsed(),
+        None => Duration::ZERO,
+    };
+    ticks_at(source(), TICK_COUNT.load(Ordering::Relaxed), elapsed)
+}
+
+/// [`now_ticks`] truncated to the wrapping `u32` ticks the features store.
+pub fn now() -> u32 {
+    now_ticks() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tick_source_ignores_real_time() {
+        assert_eq!(ticks_at(Source::Tick, 42, Duration::from_secs(100)), 42);
+    }
+
+    #[test]
+    fn wall_source_converts_elapsed_time() {
+        assert_eq!(ticks_at(Source::Wall, 0, Duration::from_secs(2)), 2 * TICKS_PER_SECOND as u64);
+        assert_eq!(ticks_at(Source::Wall, 0, Duration::from_millis(1041)), 24);
+        assert_eq!(ticks_at(Source::Wall, 0, Duration::from_millis(1042)), 25);
+    }
+
+    #[test]
+    fn source_names_round_trip() {
+        for source in [Source::Tick, Source::Wall] {
+            assert_eq!(Source::parse(source.name()), Some(source));
+            assert_eq!(Source::from_u8(source.to_u8()), source);
+        }
+        assert_eq!(Source::parse("cpu"), None);
+    }
+}